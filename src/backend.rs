@@ -0,0 +1,37 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Where the editor gets its input from and draws to. The screen is drawn by writing text and
+/// ANSI escape codes, and input is read as raw bytes that the editor decodes into keys.
+pub trait Backend: Write {
+    /// Waits for input and reads as much of it as fits in `buf`. Returns 0 once there is no
+    /// more input, and an `Interrupted` error if the screen changed size while waiting.
+    fn read_input(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Waits up to `timeout` for input. Returns true if there is some to read.
+    fn poll_input(&mut self, timeout: Duration) -> io::Result<bool>;
+
+    /// The size of the screen as (rows, columns).
+    fn size(&mut self) -> io::Result<(usize, usize)>;
+}
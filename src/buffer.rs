@@ -0,0 +1,823 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::file::{self, LineEnding};
+use crate::history::{EditKind, History, RowChange};
+use crate::mapped::MappedFile;
+use crate::row::{byte_to_cursorx, cursorx_to_byte, line_len, Row, DEFAULT_TAB_STOP};
+use crate::search::SearchPattern;
+use crate::syntax::{ends_in_comment, highlight_row, select_syntax, EditorSyntax};
+use crate::text::Text;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Files at least this big are mapped into memory rather than read.
+const MAPPED_FILE_SIZE: u64 = 64 << 20;
+
+/// The text of a file along with its undo history. Editing operations take and return
+/// cursor positions as (x, y), x counting grapheme clusters in row y. Search results are
+/// (row, byte offset into the row's line) instead.
+#[derive(Debug)]
+pub struct Buffer {
+    text: Text, // lines of text in the file
+    filename: String,
+    syntax: Option<&'static EditorSyntax>, // highlighting rules for the current filetype
+    open_comments: RefCell<Vec<bool>>,     // for the first rows, if they end inside a comment
+    history: History,                      // undo and redo stacks
+    line_ending: Option<LineEnding>,       // line ending to save with, None for a mapped file's own
+    final_newline: bool,                   // if the last line is terminated too
+    tab_stop: usize,                       // columns between tab stops when rendering
+    revision: u64,                         // goes up with every change to the text
+}
+
+impl Default for Buffer {
+    fn default() -> Buffer {
+        Buffer::new()
+    }
+}
+
+impl Buffer {
+    //////////////////// FILE I/O ////////////////////
+
+    /// An empty buffer without a file name.
+    pub fn new() -> Buffer {
+        Buffer {
+            text: Text::new(),
+            filename: String::new(),
+            syntax: None,
+            open_comments: RefCell::new(Vec::new()),
+            history: History::new(),
+            line_ending: Some(LineEnding::Lf),
+            final_newline: true,
+            tab_stop: DEFAULT_TAB_STOP,
+            revision: 0,
+        }
+    }
+
+    /// Reads `filename` into a new buffer. Files of at least `MAPPED_FILE_SIZE` are mapped
+    /// instead, with their lines found in the background.
+    pub fn open(filename: &str) -> io::Result<Buffer> {
+        let mut buffer = Buffer::new();
+        if fs::metadata(filename)?.len() >= MAPPED_FILE_SIZE {
+            let mapped = MappedFile::open(filename)?;
+            // whichever the file turns out to use once all of it is indexed
+            buffer.line_ending = None;
+            buffer.final_newline = mapped.final_newline();
+            buffer.text = Text::mapped(mapped);
+        } else {
+            let decoded = file::decode(&fs::read(filename)?);
+            buffer.line_ending = Some(decoded.line_ending);
+            buffer.final_newline = decoded.final_newline;
+            buffer.text = Text::from_lines(decoded.lines);
+        }
+        buffer.set_filename(filename.to_string());
+        Ok(buffer)
+    }
+
+    /// If the lines of a mapped file are still being found.
+    pub fn is_loading(&self) -> bool {
+        self.text.is_loading()
+    }
+
+    /// Waits until row `at` of a file that is still loading is found, or all of the file is
+    /// and it has fewer rows.
+    pub fn wait_for_row(&self, at: usize) {
+        self.text.wait_for_line(at);
+    }
+
+    /// Writes the buffer to its file and returns how many bytes were written. The buffer
+    /// stays dirty if that fails. A `PermissionDenied` error may mean the file can only be
+    /// saved with `save_in_place`.
+    pub fn save(&mut self) -> io::Result<u64> {
+        if self.filename.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no file name"));
+        }
+
+        let written = file::write_file(&self.filename, &|out| self.write_to(out))?;
+        self.history.mark_saved();
+        Ok(written)
+    }
+
+    /// Saves by overwriting the file instead of replacing it, which keeps its owner and works
+    /// without write access to its directory. A crash or a full disk partway through leaves
+    /// the file cut short.
+    pub fn save_in_place(&mut self) -> io::Result<u64> {
+        if !self.can_save_in_place() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the file can't be overwritten in place",
+            ));
+        }
+
+        let written = file::overwrite_file(&self.filename, &|out| self.write_to(out))?;
+        self.history.mark_saved();
+        Ok(written)
+    }
+
+    /// If the file exists and can be written to, and isn't the mapped file the buffer is
+    /// still reading from.
+    pub fn can_save_in_place(&self) -> bool {
+        !self.filename.is_empty()
+            && !self.text.is_mapped_from(&self.filename)
+            && file::is_writable(&self.filename)
+    }
+
+    /// Writes the contents of the file to `out` as they are saved.
+    pub fn write_to(&self, out: &mut dyn Write) -> io::Result<()> {
+        let line_ending = self
+            .line_ending
+            .unwrap_or_else(|| self.text.wait_for_line_ending());
+        self.text.write_to(out, line_ending, self.final_newline)
+    }
+
+    /// The contents of the file as they would be saved.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        // writing to a Vec can't fail
+        self.write_to(&mut buf).unwrap();
+        buf
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Changes the file the buffer is saved to, and the highlighting rules along with it.
+    pub fn set_filename(&mut self, filename: String) {
+        self.filename = filename;
+        self.syntax = select_syntax(&self.filename);
+
+        // rows are highlighted with the new rules from now on
+        self.invalidate_syntax(0);
+    }
+
+    pub fn syntax(&self) -> Option<&'static EditorSyntax> {
+        self.syntax
+    }
+
+    /// How lines are terminated when saving. For a mapped file that is still loading, that's
+    /// how the lines found so far are.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending.unwrap_or_else(|| self.text.line_ending())
+    }
+
+    /// Changes how lines are terminated when saving, as an edit that can be undone.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding, cursor: (usize, usize)) {
+        if self.line_ending() == line_ending {
+            return;
+        }
+
+        self.begin_edit(EditKind::Other, cursor);
+        let old = self.line_ending.replace(line_ending);
+        self.history.record(RowChange::LineEnding {
+            old,
+            new: Some(line_ending),
+        });
+        self.end_edit(cursor);
+    }
+
+    /// Columns between tab stops, which rows are rendered with.
+    pub fn tab_stop(&self) -> usize {
+        self.tab_stop
+    }
+
+    pub fn set_tab_stop(&mut self, width: usize) {
+        self.tab_stop = width.max(1);
+    }
+
+    /// If the buffer has been changed since it was opened or last saved.
+    pub fn is_dirty(&self) -> bool {
+        self.history.is_dirty()
+    }
+
+    //////////////////// ROW OPERATIONS ////////////////////
+
+    /// Changes whenever the text does, so what was worked out from it can tell it's stale.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.text.len()
+    }
+
+    /// The text of row `at`, without a line ending.
+    pub fn line(&self, at: usize) -> Option<Cow<'_, str>> {
+        (at < self.text.len()).then(|| self.text.line(at))
+    }
+
+    /// Row `at` rendered and highlighted. That is only worked out when asked for, as only the
+    /// rows on screen need it.
+    pub fn row(&self, at: usize) -> Option<Row> {
+        let mut row = Row::new(self.line(at)?.into_owned(), self.tab_stop);
+        let prev_open_comment = at > 0 && self.open_comment_after(at - 1);
+        highlight_row(&mut row, self.syntax, prev_open_comment);
+        Some(row)
+    }
+
+    /// If row `at` ends inside a multi-line comment. That depends on all the rows before it,
+    /// so it is remembered up to the last row it was needed for.
+    fn open_comment_after(&self, at: usize) -> bool {
+        let syntax = match self.syntax {
+            Some(syntax) if !syntax.multiline_comment_start.is_empty() => syntax,
+            _ => return false,
+        };
+
+        let mut open_comments = self.open_comments.borrow_mut();
+        let from = open_comments.len();
+        if from <= at {
+            let mut open_comment = from > 0 && open_comments[from - 1];
+            for line in self.text.lines_from(from).take(at + 1 - from) {
+                open_comment = ends_in_comment(&line, syntax, open_comment);
+                open_comments.push(open_comment);
+            }
+        }
+        open_comments.get(at).copied().unwrap_or(false)
+    }
+
+    /// Forgets the comment state from row `at` on, after it or a row before it changed.
+    fn invalidate_syntax(&mut self, at: usize) {
+        self.open_comments.get_mut().truncate(at);
+    }
+
+    fn insert_row(&mut self, at: usize, line: String) {
+        self.insert_rows(at, vec![line]);
+    }
+
+    /// Inserts `lines` as rows starting at `at`, faster than inserting them one by one.
+    fn insert_rows(&mut self, at: usize, lines: Vec<String>) {
+        if at > self.text.len() || lines.is_empty() {
+            return;
+        }
+
+        self.text.insert_lines(at, &lines);
+        self.invalidate_syntax(at);
+        self.revision += 1;
+        self.history.record(RowChange::Insert { at, lines });
+    }
+
+    fn delete_row(&mut self, at: usize) {
+        self.delete_rows(at, 1);
+    }
+
+    /// Deletes `count` rows starting at `at`, faster than deleting them one by one.
+    fn delete_rows(&mut self, at: usize, count: usize) {
+        let end = (at + count).min(self.text.len());
+        if at >= end {
+            return;
+        }
+
+        let lines: Vec<String> = self
+            .text
+            .lines_from(at)
+            .take(end - at)
+            .map(Cow::into_owned)
+            .collect();
+        self.text.remove_lines(at, end - at);
+        // the row that moved up may now start in a different comment state
+        self.invalidate_syntax(at);
+        self.revision += 1;
+        self.history.record(RowChange::Delete { at, lines });
+    }
+
+    fn set_line(&mut self, at: usize, line: String) {
+        let old = self.text.line(at).into_owned();
+        self.text.set_line(at, &line);
+        self.invalidate_syntax(at);
+        self.revision += 1;
+        self.history
+            .record(RowChange::Replace { at, old, new: line });
+    }
+
+    //////////////////// EDITOR OPERATIONS ////////////////////
+
+    /// Inserts `c` at `cursor` and returns the cursor after it. That stays on the same grapheme
+    /// cluster if `c` combined with the one before it.
+    pub fn insert_char(&mut self, cursor: (usize, usize), c: char) -> (usize, usize) {
+        let (x, y) = cursor;
+        // past the rows found so far of a file that is still loading isn't past its end yet
+        self.text.wait_for_line(y);
+        if y > self.text.len() {
+            return cursor;
+        }
+        self.begin_edit(EditKind::Insert(c), cursor);
+
+        if y == self.text.len() {
+            self.insert_row(y, String::new());
+        }
+        let mut line = self.text.line(y).into_owned();
+        let index = cursorx_to_byte(&line, x);
+        line.insert(index, c);
+        let x = byte_to_cursorx(&line, index + c.len_utf8());
+        self.set_line(y, line);
+
+        self.end_edit((x, y));
+        (x, y)
+    }
+
+    /// Inserts `text` at `cursor` as a single edit and returns the cursor after it. Lines in
+    /// `text` are separated by '\n'.
+    pub fn insert_text(&mut self, cursor: (usize, usize), text: &str) -> (usize, usize) {
+        let (x, y) = cursor;
+        self.text.wait_for_line(y);
+        if y > self.text.len() || text.is_empty() {
+            return cursor;
+        }
+        self.begin_edit(EditKind::Other, cursor);
+
+        if y == self.text.len() {
+            self.insert_row(y, String::new());
+        }
+        let line = self.text.line(y);
+        let index = cursorx_to_byte(&line, x);
+        let string_after_x = line[index..].to_string();
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        lines[0].insert_str(0, &line[..index]);
+
+        // the cursor ends up where the text ends, before what was after it
+        let last = lines.len() - 1;
+        let end = lines[last].len();
+        lines[last].push_str(&string_after_x);
+        let cursor = (byte_to_cursorx(&lines[last], end), y + last);
+
+        let rest = lines.split_off(1);
+        self.set_line(y, lines.pop().unwrap());
+        self.insert_rows(y + 1, rest);
+
+        self.end_edit(cursor);
+        cursor
+    }
+
+    /// Deletes the grapheme cluster before `cursor`, joining the row with the one above it at
+    /// the start of a row. Returns where the cursor ends up.
+    pub fn delete_char(&mut self, cursor: (usize, usize)) -> (usize, usize) {
+        let (x, y) = cursor;
+        self.text.wait_for_line(y);
+        if y >= self.text.len() || (x == 0 && y == 0) {
+            return cursor;
+        }
+
+        let line = self.text.line(y);
+        let deleted = match x {
+            0 => '\n',
+            _ => line[cursorx_to_byte(&line, x - 1)..]
+                .chars()
+                .next()
+                .unwrap_or('\n'),
+        };
+        self.begin_edit(EditKind::Delete(deleted), cursor);
+
+        let cursor = if x > 0 {
+            let mut line = self.text.line(y).into_owned();
+            if let Some((index, g)) = line.grapheme_indices(true).nth(x - 1) {
+                line.replace_range(index..index + g.len(), "");
+            }
+            self.set_line(y, line);
+            (x - 1, y)
+        } else {
+            self.join_rows(y - 1)
+        };
+
+        self.end_edit(cursor);
+        cursor
+    }
+
+    /// Breaks the row at `cursor` in two and returns the start of the new row.
+    pub fn split_row(&mut self, cursor: (usize, usize)) -> (usize, usize) {
+        let (x, y) = cursor;
+        self.text.wait_for_line(y);
+        if y > self.text.len() {
+            return cursor;
+        }
+        self.begin_edit(EditKind::Other, cursor);
+
+        if x == 0 || y == self.text.len() {
+            self.insert_row(y, String::new());
+        } else {
+            let line = self.text.line(y);
+            let index = cursorx_to_byte(&line, x);
+            let string_before_x = line[..index].to_string();
+            let string_after_x = line[index..].to_string();
+            self.set_line(y, string_before_x);
+            self.insert_row(y + 1, string_after_x);
+        }
+
+        self.end_edit((0, y + 1));
+        (0, y + 1)
+    }
+
+    /// Appends the row after row `y` to it and returns the position where they were joined.
+    pub fn join_rows(&mut self, y: usize) -> (usize, usize) {
+        self.text.wait_for_line(y + 1);
+        if y + 1 >= self.text.len() {
+            let x = self.line(y).map_or(0, |line| line_len(&line));
+            return (x, y);
+        }
+
+        let cursor = (line_len(&self.text.line(y)), y);
+        self.begin_edit(EditKind::Other, cursor);
+        let line = self.text.line(y).into_owned() + &self.text.line(y + 1);
+        self.set_line(y, line);
+        self.delete_row(y + 1);
+        self.end_edit(cursor);
+        cursor
+    }
+
+    /// The text from `start` up to `end`, with '\n' between rows. `start` must not come after
+    /// `end`.
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let (start, end) = (self.clamp_cursor(start), self.clamp_cursor(end));
+        let mut text = String::new();
+        for y in start.1..=end.1.min(self.text.len().saturating_sub(1)) {
+            let line = self.text.line(y);
+            let from = if y == start.1 {
+                cursorx_to_byte(&line, start.0)
+            } else {
+                0
+            };
+            let to = if y == end.1 {
+                cursorx_to_byte(&line, end.0)
+            } else {
+                line.len()
+            };
+            text.push_str(&line[from..to]);
+            if y != end.1 {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// Deletes the text from `start` up to `end` as a single edit, joining the rows they are
+    /// on. Returns the cursor at `start`.
+    pub fn delete_range(&mut self, start: (usize, usize), end: (usize, usize)) -> (usize, usize) {
+        let (start, end) = (self.clamp_cursor(start), self.clamp_cursor(end));
+        if (start.1, start.0) >= (end.1, end.0) {
+            return start;
+        }
+        self.begin_edit(EditKind::Other, end);
+
+        let (x, y) = start;
+        let line = self.text.line(y);
+        let string_before_x = &line[..cursorx_to_byte(&line, x)];
+        let end_line = self.text.line(end.1);
+        let string_after_end = &end_line[cursorx_to_byte(&end_line, end.0)..];
+        let line = string_before_x.to_string() + string_after_end;
+        self.set_line(y, line);
+        self.delete_rows(y + 1, end.1 - y);
+
+        self.end_edit(start);
+        start
+    }
+
+    /// Moves a cursor that is past the end of the buffer to the end of the last row.
+    fn clamp_cursor(&self, cursor: (usize, usize)) -> (usize, usize) {
+        match self.line(cursor.1) {
+            Some(line) => (cursor.0.min(line_len(&line)), cursor.1),
+            None => {
+                let y = self.text.len().saturating_sub(1);
+                (self.line(y).map_or(0, |line| line_len(&line)), y)
+            }
+        }
+    }
+
+    /// Replaces the bytes `start..end` of row `y` with `text`.
+    pub fn replace_range(&mut self, y: usize, start: usize, end: usize, text: &str) {
+        if y >= self.text.len() {
+            return;
+        }
+
+        let mut line = self.text.line(y).into_owned();
+        let before = (byte_to_cursorx(&line, start), y);
+        line.replace_range(start..end, text);
+        let after = (byte_to_cursorx(&line, start + text.len()), y);
+
+        self.begin_edit(EditKind::Other, before);
+        self.set_line(y, line);
+        self.end_edit(after);
+    }
+
+    //////////////////// HISTORY ////////////////////
+
+    /// Starts an edit at `cursor`. Every change up to the matching `end_edit` is undone in one
+    /// go, and typing or deleting one character after another is grouped by word.
+    pub fn begin_edit(&mut self, kind: EditKind, cursor: (usize, usize)) {
+        self.history.begin(kind, cursor);
+    }
+
+    /// Finishes an edit, `cursor` being where redoing it puts the cursor.
+    pub fn end_edit(&mut self, cursor: (usize, usize)) {
+        self.history.end(cursor);
+    }
+
+    /// Undoes the last edit and returns where the cursor was before it, or None if there is
+    /// nothing left to undo.
+    pub fn undo(&mut self) -> Option<(usize, usize)> {
+        let group = self.history.undo_stack.pop()?;
+
+        for change in group.changes.iter().rev() {
+            match change {
+                RowChange::Insert { at, lines } => self.delete_rows(*at, lines.len()),
+                RowChange::Delete { at, lines } => self.insert_rows(*at, lines.clone()),
+                RowChange::Replace { at, old, .. } => self.set_line(*at, old.clone()),
+                RowChange::LineEnding { old, .. } => self.line_ending = *old,
+            }
+        }
+
+        let cursor = group.cursor_before;
+        self.history.redo_stack.push(group);
+        self.history.sealed = true;
+        Some(cursor)
+    }
+
+    /// Redoes the last undone edit and returns where the cursor was after it, or None if there
+    /// is nothing to redo.
+    pub fn redo(&mut self) -> Option<(usize, usize)> {
+        let group = self.history.redo_stack.pop()?;
+
+        for change in group.changes.iter() {
+            match change {
+                RowChange::Insert { at, lines } => self.insert_rows(*at, lines.clone()),
+                RowChange::Delete { at, lines } => self.delete_rows(*at, lines.len()),
+                RowChange::Replace { at, new, .. } => self.set_line(*at, new.clone()),
+                RowChange::LineEnding { new, .. } => self.line_ending = *new,
+            }
+        }
+
+        let cursor = group.cursor_after;
+        self.history.undo_stack.push(group);
+        self.history.sealed = true;
+        Some(cursor)
+    }
+
+    //////////////////// FIND ////////////////////
+
+    /// Every match of `pattern` in the buffer as (row, byte offset into the row's line).
+    pub fn find_matches(&self, pattern: &SearchPattern) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        for (i, line) in self.text.lines().enumerate() {
+            let row_matches = pattern.find(&line);
+            matches.extend(row_matches.into_iter().map(|(start, _)| (i, start)));
+        }
+        matches
+    }
+
+    /// The first match of `pattern` at or after `pos`, wrapping around to the start of the
+    /// buffer. Only reads as far as that match.
+    pub fn find_next(
+        &self,
+        pattern: &SearchPattern,
+        pos: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let (row, from) = pos;
+        let after = (row..)
+            .zip(self.text.lines_from(row))
+            .find_map(|(i, line)| {
+                let start = pattern
+                    .find(&line)
+                    .into_iter()
+                    .map(|(start, _)| start)
+                    .find(|start| i > row || *start >= from)?;
+                Some((i, start))
+            });
+        after.or_else(|| {
+            let first = |(i, line): (usize, Cow<str>)| Some((i, pattern.find(&line).first()?.0));
+            (0..).zip(self.text.lines()).take(row + 1).find_map(first)
+        })
+    }
+
+    /// The last match of `pattern` before `pos`, wrapping around to the end of the buffer.
+    pub fn find_previous(
+        &self,
+        pattern: &SearchPattern,
+        pos: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let (row, from) = pos;
+        let len = self.text.len();
+        let last_before = |i: usize, before: usize| {
+            let line = self.text.line(i);
+            let start = pattern
+                .find(&line)
+                .into_iter()
+                .map(|(start, _)| start)
+                .rev()
+                .find(|start| *start < before)?;
+            Some((i, start))
+        };
+        let before = (0..len.min(row + 1)).rev().find_map(|i| {
+            let before = if i == row { from } else { usize::MAX };
+            last_before(i, before)
+        });
+        before.or_else(|| {
+            (row.min(len)..len)
+                .rev()
+                .find_map(|i| last_before(i, usize::MAX))
+        })
+    }
+
+    /// Like `SearchPattern::replace_at`, but looks through the rows from `pos` to the end of
+    /// the buffer. Returns the row along with the match.
+    pub fn find_replacement(
+        &self,
+        pattern: &SearchPattern,
+        pos: (usize, usize),
+        replacement: &str,
+    ) -> Option<(usize, usize, usize, String)> {
+        let (row, from) = pos;
+        for (i, line) in (row..).zip(self.text.lines_from(row)) {
+            let from = if i == row { from } else { 0 };
+            if from > line.len() {
+                continue;
+            }
+            if let Some((start, end, text)) = pattern.replace_at(&line, from, replacement) {
+                return Some((i, start, end, text));
+            }
+        }
+        None
+    }
+
+    /// Where to continue looking for matches, `next` being the byte right after the last match
+    /// or its replacement. An empty match moves on by one more character so a search can't
+    /// keep finding it.
+    pub fn skip_match(&self, row: usize, next: usize, empty_match: bool) -> (usize, usize) {
+        if !empty_match {
+            return (row, next);
+        }
+
+        let line = self.text.line(row);
+        match line[next..].chars().next() {
+            Some(c) => (row, next + c.len_utf8()),
+            None => (row + 1, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+    use std::time::Duration;
+
+    fn buffer(text: &str) -> Buffer {
+        let mut buffer = Buffer::new();
+        buffer.insert_text((0, 0), text);
+        buffer
+    }
+
+    /// `bytes` opened as a mapped file whose lines are indexed after `delay`, and read in as
+    /// usual to check the mapped one against.
+    fn mapped_and_read(name: &str, bytes: &[u8], delay: Duration) -> (Buffer, Buffer) {
+        let path = std::env::temp_dir().join(format!("ronto-buffer-{}-{name}", process::id()));
+        fs::write(&path, bytes).unwrap();
+        let filename = path.to_str().unwrap();
+        let read = Buffer::open(filename).unwrap();
+
+        let file = MappedFile::open_indexed_after(filename, delay).unwrap();
+        let mut mapped = Buffer::new();
+        mapped.line_ending = None;
+        mapped.final_newline = file.final_newline();
+        mapped.text = Text::mapped(file);
+        // the mapping stays valid once the file is gone
+        fs::remove_file(&path).unwrap();
+        (mapped, read)
+    }
+
+    fn assert_same(mapped: &Buffer, read: &Buffer) {
+        assert_eq!(mapped.num_rows(), read.num_rows());
+        assert_eq!(
+            String::from_utf8_lossy(&mapped.to_bytes()),
+            String::from_utf8_lossy(&read.to_bytes())
+        );
+        assert_eq!(mapped.to_bytes(), read.to_bytes());
+    }
+
+    #[test]
+    fn mapped_edits_match_read_ones_and_undo_to_the_same_bytes() {
+        let mut bytes = Vec::new();
+        for i in 0..200 {
+            match i % 50 {
+                7 => bytes.extend_from_slice(b"caf\xe9 \xff"),
+                8 => bytes.extend_from_slice(b"ends in a carriage return\r"),
+                _ => bytes.extend_from_slice(format!("line {i}").as_bytes()),
+            }
+            bytes.push(b'\n');
+        }
+        bytes.extend_from_slice(b"no final newline");
+        let (mut mapped, mut read) = mapped_and_read("edits", &bytes, Duration::ZERO);
+        mapped.wait_for_row(usize::MAX);
+        assert_same(&mapped, &read);
+
+        let edits: &[fn(&mut Buffer)] = &[
+            |b| _ = b.split_row((3, 63)),
+            |b| _ = b.split_row((0, 64)),
+            |b| _ = b.insert_char((2, 65), 'é'),
+            |b| _ = b.join_rows(62),
+            |b| _ = b.join_rows(130),
+            |b| _ = b.delete_char((0, 100)),
+            |b| _ = b.delete_range((2, 10), (4, 90)),
+            |b| _ = b.insert_text((1, 20), "a\nb\nc"),
+            |b| _ = b.insert_text((0, 0), "first\n"),
+            |b| b.set_line_ending(LineEnding::CrLf, (0, 0)),
+            |b| _ = b.split_row((0, b.num_rows())),
+            |b| _ = b.insert_char((0, b.num_rows()), 'x'),
+            |b| _ = b.delete_range((0, 0), (0, b.num_rows())),
+        ];
+        for edit in edits {
+            edit(&mut mapped);
+            edit(&mut read);
+            assert_same(&mapped, &read);
+        }
+
+        let edited = mapped.to_bytes();
+        while let Some(cursor) = mapped.undo() {
+            assert_eq!(Some(cursor), read.undo());
+            assert_same(&mapped, &read);
+        }
+        assert_eq!(mapped.to_bytes(), bytes);
+        while mapped.redo().is_some() {
+            read.redo();
+            assert_same(&mapped, &read);
+        }
+        assert_eq!(mapped.to_bytes(), edited);
+    }
+
+    #[test]
+    fn edits_past_the_rows_found_so_far_wait_for_them() {
+        let bytes = "line\n".repeat(100);
+        let edits: &[fn(&mut Buffer, usize)] = &[
+            |b, y| _ = b.insert_char((0, y), 'x'),
+            |b, y| _ = b.insert_text((0, y), "x\ny"),
+            |b, y| _ = b.split_row((2, y)),
+            |b, y| _ = b.delete_char((0, y)),
+        ];
+        for (i, edit) in edits.iter().enumerate() {
+            let name = format!("loading-{i}");
+            let delay = Duration::from_millis(50);
+            let (mut mapped, mut read) = mapped_and_read(&name, bytes.as_bytes(), delay);
+            assert!(mapped.is_loading());
+            // one past the last row found yet, which isn't the end of the file
+            let y = mapped.num_rows();
+            edit(&mut mapped, y);
+            edit(&mut read, y);
+            mapped.wait_for_row(usize::MAX);
+            assert_same(&mapped, &read);
+        }
+    }
+
+    #[test]
+    fn find_next_and_previous_wrap_around() {
+        let buffer = buffer("one two\nnothing\ntwo two");
+        let pattern = SearchPattern::new("two", false).unwrap();
+
+        assert_eq!(buffer.find_next(&pattern, (0, 0)), Some((0, 4)));
+        assert_eq!(buffer.find_next(&pattern, (0, 4)), Some((0, 4)));
+        assert_eq!(buffer.find_next(&pattern, (0, 5)), Some((2, 0)));
+        assert_eq!(buffer.find_next(&pattern, (2, 1)), Some((2, 4)));
+        assert_eq!(buffer.find_next(&pattern, (2, 5)), Some((0, 4)));
+
+        assert_eq!(buffer.find_previous(&pattern, (2, 4)), Some((2, 0)));
+        assert_eq!(buffer.find_previous(&pattern, (2, 0)), Some((0, 4)));
+        assert_eq!(buffer.find_previous(&pattern, (1, 3)), Some((0, 4)));
+        assert_eq!(buffer.find_previous(&pattern, (0, 4)), Some((2, 4)));
+        assert_eq!(buffer.find_previous(&pattern, (3, 0)), Some((2, 4)));
+
+        let missing = SearchPattern::new("three", false).unwrap();
+        assert_eq!(buffer.find_next(&missing, (1, 0)), None);
+        assert_eq!(buffer.find_previous(&missing, (1, 0)), None);
+        assert_eq!(Buffer::new().find_previous(&pattern, (0, 0)), None);
+    }
+
+    #[test]
+    fn revision_changes_with_the_text() {
+        let mut buffer = buffer("text");
+        let revision = buffer.revision();
+        buffer.insert_char((4, 0), '!');
+        assert_ne!(buffer.revision(), revision);
+
+        let revision = buffer.revision();
+        buffer.undo();
+        assert_ne!(buffer.revision(), revision);
+        assert_eq!(buffer.to_bytes(), b"text\n");
+    }
+}
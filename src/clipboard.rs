@@ -0,0 +1,101 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// How copied text gets to the system clipboard.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMode {
+    /// An OSC 52 escape sequence asks the terminal to set its clipboard. Works over SSH and
+    /// in tmux without any tools installed.
+    #[default]
+    Osc52,
+    /// Runs `wl-copy`, `xclip` or `pbcopy`, whichever is installed, falling back to OSC 52
+    /// when none is.
+    Command,
+    /// Copied text stays in the editor.
+    Off,
+}
+
+impl ClipboardMode {
+    /// Parses the `osc52`, `command` and `off` names used to configure the mode.
+    pub fn from_name(name: &str) -> Option<ClipboardMode> {
+        match name {
+            "osc52" => Some(ClipboardMode::Osc52),
+            "command" => Some(ClipboardMode::Command),
+            "off" => Some(ClipboardMode::Off),
+            _ => None,
+        }
+    }
+}
+
+/// The escape sequence that puts `text` on the terminal's clipboard.
+pub fn osc52_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+/// Pipes `text` into the first clipboard tool found on the PATH. Returns false if none of
+/// them is installed.
+pub fn copy_with_command(text: &str) -> io::Result<bool> {
+    // wl-copy only works under wayland and xclip only under X
+    let tools: [(&str, &[&str], bool); 3] = [
+        ("wl-copy", &[], env::var_os("WAYLAND_DISPLAY").is_some()),
+        (
+            "xclip",
+            &["-selection", "clipboard"],
+            env::var_os("DISPLAY").is_some(),
+        ),
+        ("pbcopy", &[], true),
+    ];
+    let Some((program, args, _)) = tools
+        .into_iter()
+        .find(|(program, _, usable)| *usable && is_on_path(program))
+    else {
+        return Ok(false);
+    };
+
+    // the tool must not draw over the editor
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{program} failed: {status}")));
+    }
+    Ok(true)
+}
+
+fn is_on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file())
+    })
+}
@@ -0,0 +1,187 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::fmt;
+
+/// What a command does. Every action is a command that can be bound to a key or typed at the
+/// command prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ForceQuit, // quits without asking about unsaved changes
+    Save,
+    SaveAs,
+    Find,
+    Replace,
+    Goto,
+    Set,
+    CommandPrompt,
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    NewLine,
+    Tab, // a tab, or spaces up to the next tab stop with expand_tabs
+    DeleteBack,
+    DeleteForward,
+    Up,
+    Down,
+    Left,
+    Right,
+    LineStart,
+    LineEnd,
+    PageUp,
+    PageDown,
+    ClearSearch,
+    ToggleLineEnding,
+    CycleLineNumbers,
+    ToggleWrap,
+}
+
+// every command, with the name it is typed and bound by and the arguments it needs
+const COMMANDS: [(Action, &str, &str); 30] = [
+    (Action::Quit, "quit", ""),
+    (Action::ForceQuit, "quit!", ""),
+    (Action::Save, "save", ""),
+    (Action::SaveAs, "saveas", "<path>"),
+    (Action::Find, "find", ""),
+    (Action::Replace, "replace", ""),
+    (Action::Goto, "goto", "[line[:col]]"),
+    (Action::Set, "set", "<setting> <value>"),
+    (Action::CommandPrompt, "command", ""),
+    (Action::Undo, "undo", ""),
+    (Action::Redo, "redo", ""),
+    (Action::Copy, "copy", ""),
+    (Action::Cut, "cut", ""),
+    (Action::Paste, "paste", ""),
+    (Action::NewLine, "newline", ""),
+    (Action::Tab, "tab", ""),
+    (Action::DeleteBack, "delete-back", ""),
+    (Action::DeleteForward, "delete-forward", ""),
+    (Action::Up, "up", ""),
+    (Action::Down, "down", ""),
+    (Action::Left, "left", ""),
+    (Action::Right, "right", ""),
+    (Action::LineStart, "line-start", ""),
+    (Action::LineEnd, "line-end", ""),
+    (Action::PageUp, "page-up", ""),
+    (Action::PageDown, "page-down", ""),
+    (Action::ClearSearch, "clear-search", ""),
+    (Action::ToggleLineEnding, "toggle-line-ending", ""),
+    (Action::CycleLineNumbers, "cycle-line-numbers", ""),
+    (Action::ToggleWrap, "toggle-wrap", ""),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        COMMANDS
+            .iter()
+            .find(|(_, command, _)| *command == name)
+            .map(|(action, _, _)| *action)
+    }
+
+    pub fn name(self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .map_or("", |(_, name, _)| name)
+    }
+
+    /// The arguments the command takes, such as `<path>`, empty if it takes none. Arguments in
+    /// brackets can be left out.
+    pub fn usage(self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .map_or("", |(_, _, usage)| usage)
+    }
+
+    /// If the action only moves the cursor, in which case shift selects what it moves over.
+    pub fn moves_cursor(self) -> bool {
+        matches!(
+            self,
+            Action::Up
+                | Action::Down
+                | Action::Left
+                | Action::Right
+                | Action::LineStart
+                | Action::LineEnd
+                | Action::PageUp
+                | Action::PageDown
+        )
+    }
+}
+
+/// An action and the arguments it is run with, such as `goto 120`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub action: Action,
+    pub args: String,
+}
+
+impl Command {
+    /// Parses a command as it is typed at the command prompt: its name, then its arguments.
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, args) = match line.split_once(char::is_whitespace) {
+            Some((name, args)) => (name, args.trim()),
+            None => (line, ""),
+        };
+        let action = Action::from_name(name).ok_or_else(|| format!("unknown command `{name}`"))?;
+        match (action.usage(), args.is_empty()) {
+            ("", false) => Err(format!("{name} takes no arguments")),
+            (usage, true) if usage.starts_with('<') => Err(format!("usage: {name} {usage}")),
+            _ => Ok(Command {
+                action,
+                args: args.to_string(),
+            }),
+        }
+    }
+
+    /// The names of the commands that start with `prefix`.
+    pub fn complete(prefix: &str) -> Vec<&'static str> {
+        COMMANDS
+            .iter()
+            .map(|(_, name, _)| *name)
+            .filter(|name| name.starts_with(prefix))
+            .collect()
+    }
+}
+
+impl From<Action> for Command {
+    fn from(action: Action) -> Command {
+        Command {
+            action,
+            args: String::new(),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.action.name())?;
+        if !self.args.is_empty() {
+            write!(f, " {}", self.args)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,428 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::clipboard::ClipboardMode;
+use crate::command::Command;
+use crate::keymap::{parse_keys, Keymap};
+use crate::row::DEFAULT_TAB_STOP;
+use crate::syntax::Highlight;
+use crate::view::LineNumbers;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::{Table, Value};
+
+const MAX_TAB_WIDTH: i64 = 32;
+
+// names that can be used for colors instead of SGR parameters
+const COLOR_NAMES: [(&str, &str); 17] = [
+    ("default", "39"),
+    ("black", "30"),
+    ("red", "31"),
+    ("green", "32"),
+    ("yellow", "33"),
+    ("blue", "34"),
+    ("magenta", "35"),
+    ("cyan", "36"),
+    ("white", "37"),
+    ("gray", "90"),
+    ("bright_red", "91"),
+    ("bright_green", "92"),
+    ("bright_yellow", "93"),
+    ("bright_blue", "94"),
+    ("bright_magenta", "95"),
+    ("bright_cyan", "96"),
+    ("bright_white", "97"),
+];
+
+/// Settings read from the config file and the command line.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub tab_width: usize,          // columns between tab stops
+    pub expand_tabs: bool,         // if the tab key inserts spaces up to the next tab stop
+    pub quit_times: u8,            // extra ctrl-q presses needed to quit without saving
+    pub message_timeout: Duration, // how long a status message stays up
+    pub line_numbers: LineNumbers,
+    pub wrap: bool,
+    pub clipboard: ClipboardMode,
+    pub colors: Colors,
+    pub keymap: Keymap,
+}
+
+/// The SGR parameters each highlight class is drawn with.
+#[derive(Debug, Clone)]
+pub struct Colors {
+    pub normal: String,
+    pub comment: String,
+    pub keyword: String,
+    pub type_: String,
+    pub string: String,
+    pub number: String,
+    pub search_match: String,
+    pub current_match: String,
+    pub line_number: String, // the gutter, except on the cursor's row
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            tab_width: DEFAULT_TAB_STOP,
+            expand_tabs: false,
+            quit_times: 3,
+            message_timeout: Duration::from_secs(5),
+            line_numbers: LineNumbers::default(),
+            wrap: false,
+            clipboard: ClipboardMode::default(),
+            colors: Colors::default(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Colors {
+        Colors {
+            normal: "39".to_string(),
+            comment: "36".to_string(),
+            keyword: "33".to_string(),
+            type_: "32".to_string(),
+            string: "35".to_string(),
+            number: "31".to_string(),
+            search_match: "30;46".to_string(),
+            current_match: "30;43".to_string(),
+            line_number: "90".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/ronto/config.toml`, or `~/.config/ronto/config.toml` when
+    /// `XDG_CONFIG_HOME` isn't set.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("ronto").join("config.toml"))
+    }
+
+    /// Reads the settings in the file at `path` over the current ones.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| self.read(&text))
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Reads settings written as TOML over the current ones. Colors go in a `[colors]` table
+    /// and key bindings in a `[keys]` table.
+    pub fn read(&mut self, text: &str) -> Result<(), String> {
+        let table: Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        for (key, value) in &table {
+            match (key.as_str(), value) {
+                ("colors" | "keys", Value::Table(table)) => {
+                    for (name, value) in table {
+                        self.set(&format!("{key}.{name}"), value)?;
+                    }
+                }
+                ("colors" | "keys", _) => return Err(format!("{key} must be a table")),
+                _ => self.set(key, value)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// If `key` is a setting that is either on or off.
+    pub fn is_switch(key: &str) -> bool {
+        matches!(key, "expand_tabs" | "wrap")
+    }
+
+    /// Sets `key` from a command line argument, which is taken as a number or boolean if it
+    /// looks like one.
+    pub fn set_arg(&mut self, key: &str, arg: &str) -> Result<(), String> {
+        let value = if let Ok(n) = arg.parse::<i64>() {
+            Value::Integer(n)
+        } else if let Ok(x) = arg.parse::<f64>() {
+            Value::Float(x)
+        } else if let Ok(b) = arg.parse::<bool>() {
+            Value::Boolean(b)
+        } else {
+            Value::String(arg.to_string())
+        };
+        self.set(key, &value)
+    }
+
+    /// Sets one setting, `colors.<class>` for a color and `keys.<keys>` for a key binding.
+    /// `tabstop` is another name for `tab_width`, as in vi.
+    pub fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match key {
+            "tab_width" | "tabstop" => {
+                self.tab_width = match value.as_integer() {
+                    Some(n @ 1..=MAX_TAB_WIDTH) => n as usize,
+                    _ => {
+                        return Err(format!(
+                            "tab_width must be a whole number from 1 to {MAX_TAB_WIDTH}"
+                        ))
+                    }
+                };
+            }
+            "expand_tabs" => {
+                self.expand_tabs = value.as_bool().ok_or("expand_tabs must be true or false")?;
+            }
+            "quit_times" => {
+                self.quit_times = value
+                    .as_integer()
+                    .and_then(|n| u8::try_from(n).ok())
+                    .ok_or("quit_times must be a whole number from 0 to 255")?;
+            }
+            "message_timeout" => {
+                let seconds = match value {
+                    Value::Integer(n) => *n as f64,
+                    Value::Float(x) => *x,
+                    _ => f64::NAN,
+                };
+                self.message_timeout = Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| "message_timeout must be a number of seconds")?;
+            }
+            "line_numbers" => {
+                self.line_numbers = value
+                    .as_str()
+                    .and_then(LineNumbers::from_name)
+                    .ok_or("line_numbers must be one of off, absolute, relative or hybrid")?;
+            }
+            "wrap" => {
+                self.wrap = value.as_bool().ok_or("wrap must be true or false")?;
+            }
+            "clipboard" => {
+                self.clipboard = value
+                    .as_str()
+                    .and_then(ClipboardMode::from_name)
+                    .ok_or("clipboard must be one of osc52, command or off")?;
+            }
+            _ => {
+                if let Some(name) = key.strip_prefix("colors.") {
+                    self.colors.set(name, value)?;
+                } else if let Some(keys) = key.strip_prefix("keys.") {
+                    self.bind(keys, value)?;
+                } else {
+                    return Err(format!("unknown setting `{key}`"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds `keys` to the command in `value`, or unbinds them if it is `"none"`.
+    fn bind(&mut self, keys: &str, value: &Value) -> Result<(), String> {
+        let parsed = parse_keys(keys)
+            .ok_or_else(|| format!("keys.{keys}: not a key, or keys such as \"ctrl-x ctrl-s\""))?;
+        let command = match value.as_str() {
+            Some("none") => None,
+            Some(line) => Some(Command::parse(line).map_err(|e| format!("keys.{keys}: {e}"))?),
+            None => return Err(format!("keys.{keys} must be a command")),
+        };
+        self.keymap.bind(parsed, command);
+        Ok(())
+    }
+}
+
+impl Colors {
+    /// The SGR parameters `hl` is drawn with.
+    pub fn highlight(&self, hl: Highlight) -> &str {
+        match hl {
+            Highlight::Comment | Highlight::MlComment => &self.comment,
+            Highlight::Keyword => &self.keyword,
+            Highlight::Type => &self.type_,
+            Highlight::String => &self.string,
+            Highlight::Number => &self.number,
+            Highlight::Match => &self.search_match,
+            Highlight::CurrentMatch => &self.current_match,
+            Highlight::Normal => &self.normal,
+        }
+    }
+
+    /// Sets the color of the class called `name` to a color name, or to SGR parameters such
+    /// as `"1;34"`.
+    fn set(&mut self, name: &str, value: &Value) -> Result<(), String> {
+        let color = match name {
+            "normal" => &mut self.normal,
+            "comment" => &mut self.comment,
+            "keyword" => &mut self.keyword,
+            "type" => &mut self.type_,
+            "string" => &mut self.string,
+            "number" => &mut self.number,
+            "match" => &mut self.search_match,
+            "current_match" => &mut self.current_match,
+            "line_number" => &mut self.line_number,
+            _ => return Err(format!("unknown color `{name}`")),
+        };
+        *color = parse_color(value).ok_or_else(|| {
+            format!("colors.{name} must be a color name or SGR parameters such as \"1;34\"")
+        })?;
+        Ok(())
+    }
+}
+
+fn parse_color(value: &Value) -> Option<String> {
+    let color = match value {
+        Value::Integer(n) => n.to_string(),
+        Value::String(color) => color.clone(),
+        _ => return None,
+    };
+    if let Some((_, sgr)) = COLOR_NAMES.iter().find(|(name, _)| *name == color) {
+        return Some(sgr.to_string());
+    }
+
+    let is_sgr = color
+        .split(';')
+        .all(|n| n.parse::<u8>().is_ok() && !n.starts_with('+'));
+    is_sgr.then_some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Binding;
+
+    fn set(key: &str, arg: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        config.set_arg(key, arg).map(|()| config)
+    }
+
+    fn error(key: &str, arg: &str) -> String {
+        set(key, arg).unwrap_err()
+    }
+
+    #[test]
+    fn settings_from_arguments() {
+        assert_eq!(set("tab_width", "4").unwrap().tab_width, 4);
+        assert_eq!(set("tabstop", "2").unwrap().tab_width, 2);
+        assert!(set("expand_tabs", "true").unwrap().expand_tabs);
+        assert_eq!(set("quit_times", "0").unwrap().quit_times, 0);
+        let timeout = |arg| set("message_timeout", arg).unwrap().message_timeout;
+        assert_eq!(timeout("2"), Duration::from_secs(2));
+        assert_eq!(timeout("1.5"), Duration::from_millis(1500));
+        let line_numbers = set("line_numbers", "hybrid").unwrap().line_numbers;
+        assert_eq!(line_numbers, LineNumbers::Hybrid);
+        let clipboard = set("clipboard", "off").unwrap().clipboard;
+        assert_eq!(clipboard, ClipboardMode::Off);
+
+        let color = |arg| set("colors.comment", arg).unwrap().colors.comment;
+        assert_eq!(color("gray"), "90");
+        assert_eq!(color("31"), "31");
+        assert_eq!(color("1;34"), "1;34");
+    }
+
+    #[test]
+    fn errors_say_what_was_expected() {
+        assert_eq!(error("tab_size", "4"), "unknown setting `tab_size`");
+        assert_eq!(error("tabstops", "4"), "unknown setting `tabstops`");
+        for bad in ["0", "33", "four", "2.5"] {
+            assert_eq!(
+                error("tab_width", bad),
+                "tab_width must be a whole number from 1 to 32"
+            );
+        }
+        assert_eq!(
+            error("tabstop", "0"),
+            "tab_width must be a whole number from 1 to 32"
+        );
+        assert_eq!(
+            error("expand_tabs", "yes"),
+            "expand_tabs must be true or false"
+        );
+        assert_eq!(
+            error("quit_times", "256"),
+            "quit_times must be a whole number from 0 to 255"
+        );
+        assert_eq!(
+            error("message_timeout", "-1"),
+            "message_timeout must be a number of seconds"
+        );
+        assert_eq!(
+            error("line_numbers", "sideways"),
+            "line_numbers must be one of off, absolute, relative or hybrid"
+        );
+        assert_eq!(
+            error("clipboard", "x11"),
+            "clipboard must be one of osc52, command or off"
+        );
+
+        for bad in ["purple", "1;300", "-1", "1;;2", "true"] {
+            assert_eq!(
+                error("colors.comment", bad),
+                "colors.comment must be a color name or SGR parameters such as \"1;34\"",
+                "{bad:?}"
+            );
+        }
+        assert_eq!(error("colors.nope", "red"), "unknown color `nope`");
+
+        assert_eq!(
+            error("keys.nope", "save"),
+            "keys.nope: not a key, or keys such as \"ctrl-x ctrl-s\""
+        );
+        assert_eq!(
+            error("keys.ctrl-k", "launch"),
+            "keys.ctrl-k: unknown command `launch`"
+        );
+        assert_eq!(error("keys.ctrl-k", "1"), "keys.ctrl-k must be a command");
+    }
+
+    #[test]
+    fn read_toml() {
+        let mut config = Config::default();
+        let text = r#"
+            tabstop = 4
+            wrap = true
+
+            [colors]
+            keyword = "red"
+
+            [keys]
+            "ctrl-k" = "save"
+        "#;
+        config.read(text).unwrap();
+        assert_eq!(config.tab_width, 4);
+        assert!(config.wrap);
+        assert_eq!(config.colors.keyword, "31");
+        let ctrl_k = parse_keys("ctrl-k").unwrap();
+        assert_eq!(
+            config.keymap.lookup(&ctrl_k),
+            Binding::Command(Command::parse("save").unwrap())
+        );
+
+        assert_eq!(
+            Config::default().read("colors = 3").unwrap_err(),
+            "colors must be a table"
+        );
+        assert_eq!(
+            Config::default()
+                .read("[colors]\nnope = \"red\"")
+                .unwrap_err(),
+            "unknown color `nope`"
+        );
+        assert_eq!(
+            Config::default().read("wrap = 1").unwrap_err(),
+            "wrap must be true or false"
+        );
+    }
+}
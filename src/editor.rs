@@ -0,0 +1,1455 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::backend::Backend;
+use crate::clipboard::{copy_with_command, osc52_sequence, ClipboardMode};
+use crate::command::{Action, Command};
+use crate::keymap::{keys_name, Binding};
+use crate::{
+    byte_to_cursorx, cursorx_to_byte, cursorx_to_renderx, grapheme_width, is_drawn_inverted,
+    line_len, raw_byte, str_width, truncate_to_width, Buffer, Config, Direction, EditKind, Event,
+    GotoLine, GotoTarget, Highlight, KeyCode, KeyDecoder, KeyEvent, LineEnding, MouseButton,
+    MouseEvent, MouseKind, SearchPattern, View,
+};
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+use unicode_segmentation::UnicodeSegmentation;
+
+const CTRL_H: KeyEvent = KeyEvent::ctrl('h');
+const CTRL_T: KeyEvent = KeyEvent::ctrl('t');
+// how long to wait for the rest of an escape sequence before taking ESC as the Esc key
+const ESC_TIMEOUT: Duration = Duration::from_millis(50);
+// how long a paste can stall before it's taken as ended, in case its end marker got lost
+const PASTE_TIMEOUT: Duration = Duration::from_millis(500);
+// how often the screen is redrawn while a mapped file is still being indexed
+const LOADING_REFRESH: Duration = Duration::from_millis(250);
+const MOUSE_WHEEL_LINES: isize = 3;
+// buffers with more rows than this are too slow to count every match of a search in
+const SEARCH_COUNT_ROWS: usize = 200_000;
+pub const RONTO_VERSION: &str = "0.0.1";
+const NO_FILENAME: &str = "[No Name]";
+
+/// The editor front end: a buffer and a view drawn to a backend, and the keys that edit them.
+pub struct Editor<B: Backend> {
+    buffer: Buffer,   // the file being edited
+    view: View,       // cursor and scroll position
+    backend: B,       // where keys come from and the screen is drawn to
+    keys: KeyDecoder, // input read from the backend that hasn't been handled yet
+    quit: bool,       // set once the editor should stop
+    quit_times: u8,   // how many times you must press ctrl-q without saving first to quit
+    status_message: String,
+    status_message_time: SystemTime,
+    search_pattern: Option<SearchPattern>, // highlighted until dismissed with ESC
+    search_regex: bool,                    // if search queries are regular expressions
+    search_matches: Option<SearchMatches>, // every match of the last query, for its counter
+    clipboard: String,                     // the last text copied or cut
+    config: Config,                        // settings from the config file and command line
+    pending_keys: Vec<KeyEvent>,           // the start of a chord that's being typed
+    pending_goto: Option<GotoTarget>,      // a percentage of a file that is still loading
+}
+
+impl<B: Backend> Editor<B> {
+    pub fn new(buffer: Buffer, backend: B) -> Editor<B> {
+        Editor {
+            buffer,
+            view: View::default(),
+            backend,
+            keys: KeyDecoder::new(),
+            quit: false,
+            quit_times: Config::default().quit_times,
+            status_message: String::new(),
+            status_message_time: SystemTime::now(),
+            search_pattern: None,
+            search_regex: false,
+            search_matches: None,
+            clipboard: String::new(),
+            config: Config::default(),
+            pending_keys: Vec::new(),
+            pending_goto: None,
+        }
+    }
+
+    /// Draws the screen and handles keys until the editor is quit or the input is closed.
+    pub fn run(&mut self) {
+        while !self.quit {
+            editor_refresh_screen(self);
+            editor_process_keypress(self);
+        }
+    }
+
+    pub fn refresh_screen(&mut self) {
+        editor_refresh_screen(self);
+    }
+
+    /// Reads one key and handles it, including any prompt it opens.
+    pub fn process_keypress(&mut self) {
+        editor_process_keypress(self);
+    }
+
+    /// Applies `config` to the editor and the buffer it edits.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+        editor_apply_config(self);
+    }
+
+    /// Moves the cursor to `target`, as the goto command does.
+    pub fn goto(&mut self, target: GotoTarget) {
+        // the target is centered, which needs the size of the screen
+        editor_update_window_size(self);
+        editor_goto_target(self, target);
+    }
+
+    pub fn set_status_message(&mut self, message: &str) {
+        editor_set_status_message(self, message);
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn view(&self) -> &View {
+        &self.view
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+}
+
+/// Every match of a search query, kept until the query or the buffer changes.
+struct SearchMatches {
+    query: String,
+    regex: bool,
+    revision: u64,
+    num_rows: usize, // a file that is still loading gets more rows without changing
+    matches: Vec<(usize, usize)>,
+}
+
+/// The character a key inserts when typed, if it is one.
+fn key_to_char(key: KeyEvent) -> Option<char> {
+    if key.modifiers.ctrl || key.modifiers.alt {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char(c) if !c.is_control() && raw_byte(c).is_none() => Some(c),
+        KeyCode::Tab => Some('\t'),
+        _ => None,
+    }
+}
+
+//////////////////// FILE I/O /////////////////////
+
+/// Saves to `filename`, which the buffer only keeps if that works. Returns false if the file
+/// wasn't saved.
+fn editor_save_as<B: Backend>(editor: &mut Editor<B>, filename: &str) -> bool {
+    let old = editor.buffer.filename().to_string();
+    editor.buffer.set_filename(filename.to_string());
+    let saved = editor_save(editor);
+    if !saved {
+        editor.buffer.set_filename(old);
+    }
+    saved
+}
+
+/// Saves to the buffer's file, asking for a name if it has none. Returns false if the file
+/// wasn't saved.
+fn editor_save<B: Backend>(editor: &mut Editor<B>) -> bool {
+    if editor.buffer.filename().is_empty() {
+        return match editor_prompt(editor, "Save as: {} (ESC to cancel)", None) {
+            Some(filename) if !filename.is_empty() => editor_save_as(editor, &filename),
+            _ => {
+                editor_set_status_message(editor, "Save aborted");
+                false
+            }
+        };
+    }
+
+    match editor.buffer.save() {
+        Ok(len) => {
+            editor_set_status_message(editor, &format!("{len} bytes written to disk"));
+            true
+        }
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => editor_save_in_place(editor, &e),
+        Err(e) => {
+            editor_set_status_message(editor, &format!("Can't save! I/O error: {}", e));
+            false
+        }
+    }
+}
+
+/// Offers to overwrite the file in place after `error` kept `editor_save` from replacing it,
+/// which isn't crash-safe so it's only done when confirmed. Returns false if the file wasn't
+/// saved.
+fn editor_save_in_place<B: Backend>(editor: &mut Editor<B>, error: &io::Error) -> bool {
+    if !editor.buffer.can_save_in_place() {
+        editor_set_status_message(editor, &format!("Can't save! I/O error: {}", error));
+        return false;
+    }
+
+    let question = format!("Can't save safely: {error}. Overwrite the file in place? (y/n)");
+    editor_set_status_message(editor, &question);
+    editor_refresh_screen(editor);
+    let confirmed = loop {
+        match editor_read_key(editor) {
+            Event::Key(key) => break key_to_char(key).map(|c| c.to_ascii_lowercase()) == Some('y'),
+            // the question is still waiting for an answer
+            Event::Mouse(_) | Event::Resize => editor_refresh_screen(editor),
+            Event::Paste(_) => break false,
+        }
+    };
+    if !confirmed {
+        editor_set_status_message(editor, "Save aborted");
+        return false;
+    }
+
+    match editor.buffer.save_in_place() {
+        Ok(len) => {
+            editor_set_status_message(editor, &format!("{len} bytes written to disk"));
+            true
+        }
+        Err(e) => {
+            editor_set_status_message(editor, &format!("Can't save! I/O error: {}", e));
+            false
+        }
+    }
+}
+
+//////////////////// SETTINGS ////////////////////
+
+/// Makes the view and the rest of the editor follow `editor.config`.
+fn editor_apply_config<B: Backend>(editor: &mut Editor<B>) {
+    let config = &editor.config;
+    editor.buffer.set_tab_stop(config.tab_width);
+    if editor.view.wrap != config.wrap {
+        editor.view.wrap_offset = 0;
+    }
+    editor.view.wrap = config.wrap;
+    editor.view.line_numbers = config.line_numbers;
+    editor.quit_times = config.quit_times;
+}
+
+/// Changes a setting from the command prompt, as in `set tab_width 4`. A switch can be given
+/// without a value to turn it on.
+fn editor_set<B: Backend>(editor: &mut Editor<B>, args: &str) {
+    let (name, value) = match args.split_once(char::is_whitespace) {
+        Some((name, value)) => (name, value.trim()),
+        None => (args, ""),
+    };
+    // dashes in a setting's name stand for underscores, but not in the keys of a binding
+    let key = match name.strip_prefix("keys.") {
+        Some(_) => name.to_string(),
+        None => name.replace('-', "_"),
+    };
+    let value = match value {
+        "" if Config::is_switch(&key) => "true",
+        "" => {
+            editor_set_status_message(editor, &format!("set {key} needs a value"));
+            return;
+        }
+        value => value,
+    };
+
+    match editor.config.set_arg(&key, value) {
+        Ok(()) => {
+            editor_apply_config(editor);
+            editor_set_status_message(editor, &format!("{key} = {value}"));
+        }
+        Err(e) => editor_set_status_message(editor, &e),
+    }
+}
+
+fn editor_cycle_line_numbers<B: Backend>(editor: &mut Editor<B>) {
+    let line_numbers = editor.view.line_numbers.next();
+    editor.view.line_numbers = line_numbers;
+    editor.config.line_numbers = line_numbers;
+
+    let message = format!("Line numbers: {}", line_numbers.name());
+    editor_set_status_message(editor, &message);
+}
+
+fn editor_toggle_wrap<B: Backend>(editor: &mut Editor<B>) {
+    editor.view.wrap = !editor.view.wrap;
+    editor.view.wrap_offset = 0;
+    editor.config.wrap = editor.view.wrap;
+
+    let message = if editor.view.wrap {
+        "Soft wrap on"
+    } else {
+        "Soft wrap off"
+    };
+    editor_set_status_message(editor, message);
+}
+
+fn editor_toggle_line_ending<B: Backend>(editor: &mut Editor<B>) {
+    let line_ending = match editor.buffer.line_ending() {
+        LineEnding::Lf => LineEnding::CrLf,
+        LineEnding::CrLf => LineEnding::Lf,
+    };
+    editor
+        .buffer
+        .set_line_ending(line_ending, editor.view.cursor());
+
+    let message = format!("Saving with {} line endings", line_ending.name());
+    editor_set_status_message(editor, &message);
+}
+
+//////////////////// FIND ////////////////////
+
+/// Moves the cursor to a match of `query` and returns the match counter for the prompt.
+/// Typing keeps the cursor on the current match while it still matches, and the arrow keys
+/// go to the next or previous match, wrapping around at either end of the file. Ctrl-T
+/// switches between literal and regular expression queries.
+fn editor_find<B: Backend>(
+    editor: &mut Editor<B>,
+    query_and_key: (&mut String, KeyEvent),
+) -> String {
+    let (query, key) = query_and_key;
+    editor.search_pattern = None;
+    if key.code == KeyCode::Esc {
+        return String::new();
+    }
+
+    if key == CTRL_T {
+        editor.search_regex = !editor.search_regex;
+    }
+    let mode = if editor.search_regex { " [regex]" } else { "" };
+
+    if query.is_empty() {
+        return mode.to_string();
+    }
+    let pattern = match SearchPattern::new(query, editor.search_regex) {
+        Ok(pattern) => editor.search_pattern.insert(pattern),
+        Err(e) => return format!("{mode} [{e}]"),
+    };
+    if key.code == KeyCode::Enter {
+        return String::new();
+    }
+
+    let line = editor.buffer.line(editor.view.cursor_y).unwrap_or_default();
+    let cursor = (
+        editor.view.cursor_y,
+        cursorx_to_byte(&line, editor.view.cursor_x),
+    );
+    let found = match key.code {
+        KeyCode::Right | KeyCode::Down => {
+            let (row, index) = cursor;
+            let next = line[index..].chars().next().map_or(1, char::len_utf8);
+            editor.buffer.find_next(pattern, (row, index + next))
+        }
+        KeyCode::Left | KeyCode::Up => editor.buffer.find_previous(pattern, cursor),
+        _ => editor.buffer.find_next(pattern, cursor),
+    };
+    let Some((row, index)) = found else {
+        return format!("{mode} [no matches]");
+    };
+
+    editor.view.cursor_y = row;
+    let line = editor.buffer.line(row).unwrap_or_default();
+    editor.view.cursor_x = byte_to_cursorx(&line, index);
+    if !editor.view.is_cursor_visible(&editor.buffer) {
+        editor.view.center_cursor(&editor.buffer);
+    }
+
+    match editor_find_matches(editor, query) {
+        Some(matches) => {
+            let current = matches.partition_point(|m| *m < (row, index));
+            format!("{mode} [match {} of {}]", current + 1, matches.len())
+        }
+        None => mode.to_string(),
+    }
+}
+
+/// Every match of the search pattern, which is `query`, in the file as (row, byte offset
+/// into the row's line). They are only looked for again once the query or the buffer
+/// changes, and not at all in a buffer too big to go through on every key.
+fn editor_find_matches<'a, B: Backend>(
+    editor: &'a mut Editor<B>,
+    query: &str,
+) -> Option<&'a [(usize, usize)]> {
+    let num_rows = editor.buffer.num_rows();
+    if num_rows > SEARCH_COUNT_ROWS {
+        return None;
+    }
+    let pattern = editor.search_pattern.as_ref()?;
+
+    let revision = editor.buffer.revision();
+    let cached = editor.search_matches.as_ref().is_some_and(|cached| {
+        cached.query == query
+            && cached.regex == editor.search_regex
+            && cached.revision == revision
+            && cached.num_rows == num_rows
+    });
+    if !cached {
+        editor.search_matches = Some(SearchMatches {
+            query: query.to_string(),
+            regex: editor.search_regex,
+            revision,
+            num_rows,
+            matches: editor.buffer.find_matches(pattern),
+        });
+    }
+    editor
+        .search_matches
+        .as_ref()
+        .map(|cached| &cached.matches[..])
+}
+
+fn editor_search<B: Backend>(editor: &mut Editor<B>) {
+    let saved_cx = editor.view.cursor_x;
+    let saved_cy = editor.view.cursor_y;
+    let saved_coloff = editor.view.column_offset;
+    let saved_rowoff = editor.view.row_offset;
+    let saved_wrapoff = editor.view.wrap_offset;
+
+    let query = editor_prompt(
+        editor,
+        "Search: {} (Use ESC/Arrows/Enter, Ctrl-T = regex)",
+        Some(editor_find),
+    );
+
+    match query {
+        Some(query) => {
+            if let Err(e) = SearchPattern::new(&query, editor.search_regex) {
+                editor_set_status_message(editor, &e);
+            }
+        }
+        None => {
+            editor.view.cursor_x = saved_cx;
+            editor.view.cursor_y = saved_cy;
+            editor.view.column_offset = saved_coloff;
+            editor.view.row_offset = saved_rowoff;
+            editor.view.wrap_offset = saved_wrapoff;
+        }
+    }
+}
+
+fn editor_replace<B: Backend>(editor: &mut Editor<B>) {
+    let saved_cx = editor.view.cursor_x;
+    let saved_cy = editor.view.cursor_y;
+    let saved_coloff = editor.view.column_offset;
+    let saved_rowoff = editor.view.row_offset;
+    let saved_wrapoff = editor.view.wrap_offset;
+
+    let query = editor_prompt(
+        editor,
+        "Replace: {} (Use ESC/Arrows/Enter, Ctrl-T = regex)",
+        Some(editor_find),
+    );
+    let replacement = match query {
+        Some(query) if !query.is_empty() => {
+            if let Err(e) = SearchPattern::new(&query, editor.search_regex) {
+                editor_set_status_message(editor, &e);
+                return;
+            }
+            editor_prompt(editor, "Replace with: {} (ESC to cancel)", None)
+        }
+        _ => None,
+    };
+
+    let replacement = match (&editor.search_pattern, replacement) {
+        (Some(_), Some(replacement)) => replacement,
+        _ => {
+            editor.view.cursor_x = saved_cx;
+            editor.view.cursor_y = saved_cy;
+            editor.view.column_offset = saved_coloff;
+            editor.view.row_offset = saved_rowoff;
+            editor.view.wrap_offset = saved_wrapoff;
+            editor_set_status_message(editor, "Replace aborted");
+            return;
+        }
+    };
+
+    let replaced = editor_replace_matches(editor, &replacement);
+    editor.search_pattern = None;
+    editor_set_status_message(editor, &format!("Replaced {replaced} occurrence(s)"));
+}
+
+/// Walks through the matches of the search pattern from the cursor onwards, wrapping around
+/// at the end of the file, and asks what to do with each one. Returns how many were replaced.
+fn editor_replace_matches<B: Backend>(editor: &mut Editor<B>, replacement: &str) -> usize {
+    let line = editor.buffer.line(editor.view.cursor_y).unwrap_or_default();
+    let start_row = editor.view.cursor_y;
+    // where the walk stops once it has wrapped around, moves as replacements before it in
+    // the same row change the row's length
+    let mut limit = cursorx_to_byte(&line, editor.view.cursor_x);
+
+    let mut pos = (start_row, limit);
+    let mut wrapped = false;
+    let mut after_match = false;
+    let mut replace_all = false;
+    let mut replaced: usize = 0;
+
+    loop {
+        let found = match &editor.search_pattern {
+            Some(pattern) => editor.buffer.find_replacement(pattern, pos, replacement),
+            None => None,
+        };
+        let (row, start, end, text) = match found {
+            Some(found) => found,
+            None if !wrapped => {
+                wrapped = true;
+                pos = (0, 0);
+                continue;
+            }
+            None => break,
+        };
+        if wrapped && (row > start_row || (row == start_row && start >= limit)) {
+            break;
+        }
+        // same as regex's own replacing, an empty match right after another match is skipped
+        if start == end && after_match && (row, start) == pos {
+            pos = editor.buffer.skip_match(row, start, true);
+            after_match = false;
+            continue;
+        }
+
+        editor.view.cursor_y = row;
+        let line = editor.buffer.line(row).unwrap_or_default();
+        editor.view.cursor_x = byte_to_cursorx(&line, start);
+
+        if !replace_all {
+            if !editor.view.is_cursor_visible(&editor.buffer) {
+                editor.view.center_cursor(&editor.buffer);
+            }
+            editor_set_status_message(editor, "Replace this match? (y)es (n)o (a)ll (q)uit");
+            editor_refresh_screen(editor);
+            let key = match editor_read_key(editor) {
+                Event::Key(key) => key,
+                Event::Paste(_) | Event::Mouse(_) | Event::Resize => continue,
+            };
+
+            match key_to_char(key).map(|c| c.to_ascii_lowercase()) {
+                Some('y') => {}
+                Some('n') => {
+                    pos = editor.buffer.skip_match(row, end, start == end);
+                    after_match = start != end;
+                    continue;
+                }
+                Some('a') => {
+                    replace_all = true;
+                    // everything that's left is undone in one go
+                    editor
+                        .buffer
+                        .begin_edit(EditKind::Other, editor.view.cursor());
+                }
+                Some('q') => break,
+                _ if key.code == KeyCode::Esc => break,
+                _ => continue,
+            }
+        }
+
+        editor.buffer.replace_range(row, start, end, &text);
+        replaced += 1;
+
+        if wrapped && row == start_row {
+            limit = limit.max(end) + text.len() - (end - start);
+        }
+        pos = editor
+            .buffer
+            .skip_match(row, start + text.len(), start == end);
+        after_match = start != end;
+    }
+
+    if replace_all {
+        editor.buffer.end_edit(editor.view.cursor());
+    }
+    replaced
+}
+
+//////////////////// EDITOR OPERATIONS ////////////////////
+
+fn editor_insert_char<B: Backend>(editor: &mut Editor<B>, c: char) {
+    let cursor = editor.buffer.insert_char(editor.view.cursor(), c);
+    editor.view.set_cursor(cursor);
+}
+
+/// Inserts spaces up to the next tab stop.
+fn editor_insert_tab<B: Backend>(editor: &mut Editor<B>) {
+    let (cx, cy) = editor.view.cursor();
+    let tab_stop = editor.buffer.tab_stop();
+    let rx = editor
+        .buffer
+        .line(cy)
+        .map_or(0, |line| cursorx_to_renderx(&line, cx, tab_stop));
+    let spaces = tab_stop - rx % tab_stop;
+    for _ in 0..spaces {
+        editor_insert_char(editor, ' ');
+    }
+}
+
+fn editor_del_char<B: Backend>(editor: &mut Editor<B>) {
+    let cursor = editor.buffer.delete_char(editor.view.cursor());
+    editor.view.set_cursor(cursor);
+}
+
+fn editor_insert_new_line<B: Backend>(editor: &mut Editor<B>) {
+    let cursor = editor.buffer.split_row(editor.view.cursor());
+    editor.view.set_cursor(cursor);
+}
+
+/// Inserts pasted text as it is in place of the selection, as a single edit.
+fn editor_paste<B: Backend>(editor: &mut Editor<B>, text: &str) {
+    // other control characters would end up in the file where they can't be seen
+    let text: String = text
+        .chars()
+        .filter(|&c| c == '\n' || c == '\t' || (!c.is_control() && raw_byte(c).is_none()))
+        .collect();
+    editor
+        .buffer
+        .begin_edit(EditKind::Other, editor.view.cursor());
+    editor_delete_selection(editor);
+    let cursor = editor.buffer.insert_text(editor.view.cursor(), &text);
+    editor.view.set_cursor(cursor);
+    editor.buffer.end_edit(cursor);
+}
+
+/// Copies the selected text to the clipboard.
+fn editor_copy<B: Backend>(editor: &mut Editor<B>) {
+    let Some((start, end)) = editor.view.selection() else {
+        editor_set_status_message(editor, "Nothing selected");
+        return;
+    };
+    editor.clipboard = editor.buffer.text_range(start, end);
+
+    let exported = match editor.config.clipboard {
+        ClipboardMode::Osc52 => editor_write_osc52(editor),
+        ClipboardMode::Command => match copy_with_command(&editor.clipboard) {
+            Ok(true) => Ok(()),
+            Ok(false) => editor_write_osc52(editor),
+            Err(e) => Err(e),
+        },
+        ClipboardMode::Off => Ok(()),
+    };
+    let message = match exported {
+        Ok(()) => format!("{} bytes copied", editor.clipboard.len()),
+        Err(e) => format!("Copied, but not to the system clipboard: {e}"),
+    };
+    editor_set_status_message(editor, &message);
+}
+
+/// Asks the terminal to put the clipboard's text on the system clipboard.
+fn editor_write_osc52<B: Backend>(editor: &mut Editor<B>) -> io::Result<()> {
+    let sequence = osc52_sequence(&editor.clipboard);
+    editor.backend.write_all(sequence.as_bytes())?;
+    editor.backend.flush()
+}
+
+/// Deletes the selected text. Returns false if nothing was selected.
+fn editor_delete_selection<B: Backend>(editor: &mut Editor<B>) -> bool {
+    let Some((start, end)) = editor.view.selection() else {
+        return false;
+    };
+    let cursor = editor.buffer.delete_range(start, end);
+    editor.view.set_cursor(cursor);
+    editor.view.anchor = None;
+    true
+}
+
+fn editor_undo<B: Backend>(editor: &mut Editor<B>) {
+    match editor.buffer.undo() {
+        Some(cursor) => editor.view.set_cursor(cursor),
+        None => editor_set_status_message(editor, "Already at oldest change"),
+    }
+}
+
+fn editor_redo<B: Backend>(editor: &mut Editor<B>) {
+    match editor.buffer.redo() {
+        Some(cursor) => editor.view.set_cursor(cursor),
+        None => editor_set_status_message(editor, "Already at newest change"),
+    }
+}
+
+/// Moves the cursor to where `args` points, asking where to go if it is empty.
+fn editor_goto<B: Backend>(editor: &mut Editor<B>, args: &str) {
+    let args = if args.is_empty() {
+        match editor_prompt(editor, "Go to: {} (line[:col], +N, -N or N%)", None) {
+            Some(args) if !args.trim().is_empty() => args,
+            _ => return,
+        }
+    } else {
+        args.to_string()
+    };
+
+    match GotoTarget::parse(&args) {
+        Some(target) => editor_goto_target(editor, target),
+        None => editor_set_status_message(editor, &format!("Can't go to `{}`", args.trim())),
+    }
+}
+
+/// Moves the cursor to `target`. A percentage of a file that is still loading isn't known
+/// yet, so the cursor only goes there once the file is loaded, unless a key is pressed first.
+fn editor_goto_target<B: Backend>(editor: &mut Editor<B>, target: GotoTarget) {
+    if matches!(target.line, GotoLine::Percent(_)) && editor.buffer.is_loading() {
+        editor.pending_goto = Some(target);
+        editor_set_status_message(editor, "Going there once the file is loaded...");
+    } else {
+        editor.view.goto(&editor.buffer, target);
+    }
+}
+
+//////////////////// COMMAND PROMPT ////////////////////
+
+/// Reads a command such as `saveas notes.txt` or `set tab_width 4` and runs it.
+fn editor_command_prompt<B: Backend>(editor: &mut Editor<B>) {
+    let line = match editor_prompt(editor, ": {}", Some(editor_complete_command)) {
+        Some(line) if !line.trim().is_empty() => line,
+        _ => return,
+    };
+    match Command::parse(&line) {
+        Ok(command) => editor_run_command(editor, &command),
+        Err(e) => editor_set_status_message(editor, &e),
+    }
+}
+
+/// Completes the command's name on tab, as far as the names it could be agree, and hints at
+/// the arguments the command takes.
+fn editor_complete_command<B: Backend>(
+    _editor: &mut Editor<B>,
+    line_and_key: (&mut String, KeyEvent),
+) -> String {
+    let (line, key) = line_and_key;
+    if matches!(key.code, KeyCode::Esc | KeyCode::Enter) {
+        return String::new();
+    }
+
+    // past the name, only the arguments are left to hint at
+    let name = line.trim_start();
+    if let Some((name, args)) = name.split_once(char::is_whitespace) {
+        return match Action::from_name(name) {
+            Some(action) if args.is_empty() => action.usage().to_string(),
+            _ => String::new(),
+        };
+    }
+
+    let names = Command::complete(name);
+    if key.code == KeyCode::Tab && !names.is_empty() {
+        // command names are ASCII, so any byte they differ at is a char boundary
+        let mut prefix = names[0];
+        for other in &names[1..] {
+            let len = prefix
+                .bytes()
+                .zip(other.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            prefix = &prefix[..len];
+        }
+        *line = prefix.to_string();
+
+        if let [name] = names[..] {
+            let usage = Action::from_name(name).map_or("", Action::usage);
+            if !usage.is_empty() {
+                line.push(' ');
+                return usage.to_string();
+            }
+        } else {
+            return format!("  ({})", names.join(" "));
+        }
+    }
+
+    match Action::from_name(line) {
+        Some(action) if !action.usage().is_empty() => format!(" {}", action.usage()),
+        _ => String::new(),
+    }
+}
+
+//////////////////// INPUT /////////////////////
+
+/// Reads the next key or paste from the backend. Returns `Event::Resize` if the screen changed
+/// size while waiting, and quits the editor once there is no more input.
+fn editor_read_key<B: Backend>(editor: &mut Editor<B>) -> Event {
+    loop {
+        if let Some(event) = editor.keys.next_event(false) {
+            return event;
+        }
+
+        // what's left may be the start of an escape sequence, if the rest doesn't follow
+        // right away it was typed on its own. A paste gets longer, its end may just be slow
+        if !editor.keys.is_empty() {
+            let timeout = if editor.keys.in_paste() {
+                PASTE_TIMEOUT
+            } else {
+                ESC_TIMEOUT
+            };
+            match editor.backend.poll_input(timeout) {
+                Ok(true) => {}
+                Ok(false) => {
+                    if let Some(event) = editor.keys.next_event(true) {
+                        return event;
+                    }
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Event::Resize,
+                Err(_) => {}
+            }
+        }
+
+        // nothing was typed yet, but the line count on the screen goes up as lines are found
+        if editor.buffer.is_loading() {
+            match editor.backend.poll_input(LOADING_REFRESH) {
+                Ok(false) => {
+                    editor_refresh_screen(editor);
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Event::Resize,
+                _ => {}
+            }
+        }
+
+        let mut buf = [0u8; 4096];
+        match editor.backend.read_input(&mut buf) {
+            Ok(len) if len > 0 => editor.keys.push(&buf[..len]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Event::Resize,
+            _ => {
+                // cancels whatever prompt is open, so the main loop gets to see the editor
+                // is done
+                editor.quit = true;
+                return Event::Key(KeyEvent::plain(KeyCode::Esc));
+            }
+        }
+    }
+}
+
+/// Called by `editor_prompt` after every keypress with the current input, which it may change,
+/// as completing it on tab does. The returned text is shown right after the input.
+type PromptCallback<B> = fn(&mut Editor<B>, (&mut String, KeyEvent)) -> String;
+
+/// Reads a line of input in the message bar. Returns None if the prompt was cancelled with ESC,
+/// the input may be empty otherwise.
+fn editor_prompt<B: Backend>(
+    editor: &mut Editor<B>,
+    prompt: &str,
+    callback: Option<PromptCallback<B>>,
+) -> Option<String> {
+    let mut buf = String::with_capacity(128);
+    let mut hint = String::new();
+
+    loop {
+        let message = prompt.replace("{}", &(buf.clone() + &hint));
+        editor_set_status_message(editor, &message);
+        editor_refresh_screen(editor);
+
+        let key = match editor_read_key(editor) {
+            Event::Key(key) => key,
+            Event::Paste(text) => {
+                // the prompt is a single line, so only the first line is kept
+                let line = text.lines().next().unwrap_or("");
+                buf.extend(line.chars().filter(|c| !c.is_control()));
+                // to the callback this looks like the last character was typed
+                match buf.chars().last() {
+                    Some(c) => KeyEvent::plain(KeyCode::Char(c)),
+                    None => continue,
+                }
+            }
+            Event::Mouse(_) | Event::Resize => continue,
+        };
+        match key {
+            KeyEvent {
+                code: KeyCode::Delete | KeyCode::Backspace,
+                ..
+            }
+            | CTRL_H => {
+                buf.pop();
+            }
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            } => {
+                editor_set_status_message(editor, "");
+                if let Some(callback) = callback {
+                    callback(editor, (&mut buf, key));
+                }
+                return None;
+            }
+            KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            } => {
+                editor_set_status_message(editor, "");
+                if let Some(callback) = callback {
+                    callback(editor, (&mut buf, key));
+                }
+                return Some(buf);
+            }
+            _ => {
+                if let Some(c) = key_to_char(key).filter(|c| *c != '\t') {
+                    buf.push(c);
+                }
+            }
+        }
+
+        if let Some(callback) = callback {
+            hint = callback(editor, (&mut buf, key));
+        }
+    }
+}
+
+fn editor_process_keypress<B: Backend>(editor: &mut Editor<B>) {
+    let event = editor_read_key(editor);
+    // doing anything else gives up on a goto that waits for the file to load
+    if !matches!(event, Event::Resize) {
+        editor.pending_goto = None;
+    }
+
+    let key = match event {
+        Event::Key(key) => key,
+        Event::Paste(text) => {
+            editor.pending_keys.clear();
+            editor_paste(editor, &text);
+            editor.quit_times = editor.config.quit_times;
+            return;
+        }
+        Event::Mouse(mouse) => {
+            editor.pending_keys.clear();
+            editor_process_mouse(editor, mouse);
+            return;
+        }
+        Event::Resize => return,
+    };
+
+    editor.pending_keys.push(key);
+    let command = match editor.config.keymap.lookup(&editor.pending_keys) {
+        // the rest of the chord is hinted at in the message bar
+        Binding::Prefix => return,
+        Binding::Command(command) => Some(command),
+        Binding::Unbound if editor.pending_keys.len() > 1 => {
+            let message = format!("{} is not bound", keys_name(&editor.pending_keys));
+            editor.pending_keys.clear();
+            editor_set_status_message(editor, &message);
+            return;
+        }
+        Binding::Unbound => None,
+    };
+    editor.pending_keys.clear();
+
+    let action = command.as_ref().map(|command| command.action);
+    // shift with a key that moves the cursor selects the text it moves over
+    let selecting = key.modifiers.shift && action.is_some_and(Action::moves_cursor);
+    if selecting && editor.view.anchor.is_none() {
+        editor.view.anchor = Some(editor.view.cursor());
+    }
+
+    match &command {
+        Some(command) => editor_run_command(editor, command),
+        None => {
+            if let Some(c) = key_to_char(key) {
+                editor_insert_char(editor, c);
+            }
+        }
+    }
+
+    // copying leaves the selection in place, anything else ends it
+    if !selecting && action != Some(Action::Copy) {
+        editor.view.anchor = None;
+    }
+    // quitting has to be asked for again and again
+    if action != Some(Action::Quit) {
+        editor.quit_times = editor.config.quit_times;
+    }
+}
+
+fn editor_run_command<B: Backend>(editor: &mut Editor<B>, command: &Command) {
+    match command.action {
+        Action::Quit => editor_quit(editor),
+        Action::ForceQuit => editor.quit = true,
+        Action::Save => {
+            editor_save(editor);
+        }
+        Action::SaveAs => {
+            editor_save_as(editor, &command.args);
+        }
+        Action::Find => editor_search(editor),
+        Action::Replace => editor_replace(editor),
+        Action::Goto => editor_goto(editor, &command.args),
+        Action::Set => editor_set(editor, &command.args),
+        Action::CommandPrompt => editor_command_prompt(editor),
+        Action::Undo => editor_undo(editor),
+        Action::Redo => editor_redo(editor),
+        Action::Copy => editor_copy(editor),
+        Action::Cut => {
+            editor_copy(editor);
+            editor_delete_selection(editor);
+        }
+        Action::Paste => {
+            let text = editor.clipboard.clone();
+            editor_paste(editor, &text);
+        }
+        Action::NewLine => editor_insert_new_line(editor),
+        Action::Tab if editor.config.expand_tabs => editor_insert_tab(editor),
+        Action::Tab => editor_insert_char(editor, '\t'),
+        Action::DeleteBack => {
+            if !editor_delete_selection(editor) {
+                editor_del_char(editor);
+            }
+        }
+        Action::DeleteForward => {
+            if !editor_delete_selection(editor) {
+                editor_move_cursor(KeyCode::Right, editor);
+                editor_del_char(editor);
+            }
+        }
+        Action::Up => editor_move_cursor(KeyCode::Up, editor),
+        Action::Down => editor_move_cursor(KeyCode::Down, editor),
+        Action::Left => editor_move_cursor(KeyCode::Left, editor),
+        Action::Right => editor_move_cursor(KeyCode::Right, editor),
+        Action::LineStart => editor.view.cursor_x = 0,
+        Action::LineEnd => {
+            if let Some(line) = editor.buffer.line(editor.view.cursor_y) {
+                editor.view.cursor_x = line_len(&line);
+            }
+        }
+        Action::PageUp => editor_page(editor, KeyCode::Up),
+        Action::PageDown => editor_page(editor, KeyCode::Down),
+        Action::ClearSearch => editor.search_pattern = None,
+        Action::ToggleLineEnding => editor_toggle_line_ending(editor),
+        Action::CycleLineNumbers => editor_cycle_line_numbers(editor),
+        Action::ToggleWrap => editor_toggle_wrap(editor),
+    }
+}
+
+/// Quits, unless there are unsaved changes and ctrl-q hasn't been pressed enough times yet.
+fn editor_quit<B: Backend>(editor: &mut Editor<B>) {
+    if editor.buffer.is_dirty() && editor.quit_times > 0 {
+        let keys = match editor.config.keymap.keys_for(Action::Quit) {
+            Some(keys) => format!("Press {}", keys_name(keys)),
+            None => "Quit".to_string(),
+        };
+        editor_set_status_message(
+            editor,
+            &format!(
+                "WARNING!!! File has unsaved changes. {keys} {} more times to quit.",
+                editor.quit_times
+            ),
+        );
+        editor.quit_times -= 1;
+        return;
+    }
+    editor.quit = true;
+}
+
+/// Moves the cursor a screen up or down, `direction` being the arrow key for the way to go.
+fn editor_page<B: Backend>(editor: &mut Editor<B>, direction: KeyCode) {
+    let view = &mut editor.view;
+    if direction == KeyCode::Up {
+        view.move_to_screen_row(&editor.buffer, 0);
+    } else {
+        let last = view.screen_rows.saturating_sub(1);
+        view.move_to_screen_row(&editor.buffer, last);
+    }
+
+    let mut times = editor.view.screen_rows;
+    while times > 0 {
+        editor_move_cursor(direction, editor);
+        times -= 1;
+    }
+}
+
+/// Clicking puts the cursor where the mouse is and dragging selects the text from there,
+/// shift-click selects up to where the mouse is. The wheel scrolls the screen.
+fn editor_process_mouse<B: Backend>(editor: &mut Editor<B>, mouse: MouseEvent) {
+    let view = &mut editor.view;
+    match mouse.kind {
+        MouseKind::Press(MouseButton::Left) if mouse.row < view.screen_rows => {
+            let cursor = view.screen_to_cursor(&editor.buffer, mouse.row, mouse.col);
+            // shift-click extends the selection instead of starting a new one
+            if !mouse.modifiers.shift {
+                view.anchor = Some(cursor);
+            } else if view.anchor.is_none() {
+                view.anchor = Some(view.cursor());
+            }
+            view.set_cursor(cursor);
+        }
+        MouseKind::Drag(MouseButton::Left) if view.anchor.is_some() => {
+            // dragging past the bottom of the text area scrolls down along with the cursor
+            let cursor = view.screen_to_cursor(&editor.buffer, mouse.row, mouse.col);
+            view.set_cursor(cursor);
+        }
+        MouseKind::Release(MouseButton::Left) if view.selection().is_none() => {
+            // a click without a drag doesn't leave a selection behind
+            view.anchor = None;
+        }
+        MouseKind::ScrollUp => view.scroll_by(&editor.buffer, -MOUSE_WHEEL_LINES),
+        MouseKind::ScrollDown => view.scroll_by(&editor.buffer, MOUSE_WHEEL_LINES),
+        _ => {}
+    }
+}
+
+fn editor_move_cursor<B: Backend>(key: KeyCode, editor: &mut Editor<B>) {
+    let direction = match key {
+        KeyCode::Up => Direction::Up,
+        KeyCode::Down => Direction::Down,
+        KeyCode::Left => Direction::Left,
+        KeyCode::Right => Direction::Right,
+        _ => return,
+    };
+    editor.view.move_cursor(&editor.buffer, direction);
+}
+
+//////////////////// OUTPUT /////////////////////
+
+fn editor_set_status_message<B: Backend>(editor: &mut Editor<B>, message: &str) {
+    editor.status_message = message.to_string();
+    editor.status_message_time = SystemTime::now();
+}
+
+fn editor_refresh_screen<B: Backend>(editor: &mut Editor<B>) {
+    editor_update_window_size(editor);
+    if !editor.buffer.is_loading() {
+        if let Some(target) = editor.pending_goto.take() {
+            editor.view.goto(&editor.buffer, target);
+        }
+    }
+    editor.view.scroll(&editor.buffer);
+    let mut buf_writer = Vec::new();
+
+    // hide the cursor
+    buf_writer.write_all(b"\x1b[?25l").unwrap();
+    // ansi cursor home code
+    buf_writer.write_all(b"\x1b[H").unwrap();
+
+    editor_draw_rows(&mut buf_writer, editor);
+    editor_draw_status_bar(&mut buf_writer, editor);
+    editor_draw_message_bar(&mut buf_writer, editor);
+
+    // CONSIDERATION: rewrite without making a heap allocation
+    // let mut buf = [0u8, 32];
+    // let mut cursor = io::Cursor::new(&mut buf[..]);
+    // write!(
+    //     &mut cursor,
+    //     "\x1b[{};{}H",
+    //     16u16,
+    //     16u16,
+    //     // editor.cursor_y,
+    //     // editor.cursor_x
+    // )?;
+    // buf_writer.write(cursor.get_ref())?;
+
+    let (cursor_row, cursor_col) = editor.view.cursor_on_screen(&editor.buffer);
+    let cursor_pos = format!(
+        "\x1b[{};{}H",
+        cursor_row + 1,
+        editor.view.gutter_width + cursor_col + 1
+    );
+    buf_writer.write_all(cursor_pos.as_bytes()).unwrap();
+
+    // show the cursor
+    buf_writer.write_all(b"\x1b[?25h").unwrap();
+
+    let backend = &mut editor.backend;
+    if backend
+        .write_all(&buf_writer)
+        .and_then(|_| backend.flush())
+        .is_err()
+    {
+        editor.quit = true;
+    }
+}
+
+fn editor_update_window_size<B: Backend>(editor: &mut Editor<B>) {
+    // the old size is kept if the backend can't tell
+    let mut cols = editor.view.gutter_width + editor.view.screen_cols;
+    if let Ok((new_rows, new_cols)) = editor.backend.size() {
+        // room for the status and message bars, a tiny screen still gets one row of text
+        editor.view.screen_rows = new_rows.saturating_sub(2).max(1);
+        cols = new_cols;
+    }
+    // the gutter grows with the number of rows, so it's worked out again on every refresh
+    editor.view.set_screen_cols(&editor.buffer, cols.max(1));
+}
+
+fn editor_draw_rows<B: Backend>(buf_writer: &mut Vec<u8>, editor: &Editor<B>) {
+    let screen_cols = editor.view.gutter_width + editor.view.screen_cols;
+    let num_of_rows = editor.buffer.num_rows();
+    let lines = editor.view.screen_lines(&editor.buffer);
+    for y in 0..editor.view.screen_rows {
+        let line = lines.get(y).filter(|(filerow, ..)| *filerow < num_of_rows);
+        if let Some(&(filerow, first_col, last_col)) = line {
+            // a wrapped row is numbered on its first line only
+            if !editor.view.wrap || first_col == 0 {
+                editor_draw_line_number(buf_writer, editor, filerow);
+            } else {
+                write!(buf_writer, "{:1$}", "", editor.view.gutter_width).unwrap();
+            }
+            editor_draw_row(buf_writer, editor, filerow, first_col, last_col);
+        } else if num_of_rows == 0 && y == editor.view.screen_rows / 3 {
+            // CONSIDERATION: rewrite without making a heap allocation
+            // let mut buf = [0u8, 80];
+            // let welcome = write!(buf, "Ronto editor --version {}", RONTO_VERSION);
+
+            let welcome = format!("Ronto editor -- version {RONTO_VERSION}");
+            let welcome = truncate_to_width(&welcome, screen_cols - 1);
+            let mut padding = (screen_cols - welcome.len()) / 2;
+            buf_writer.write_all(b"~").unwrap();
+            padding = padding.saturating_sub(1);
+            while padding > 0 {
+                buf_writer.write_all(b" ").unwrap();
+                padding -= 1;
+            }
+
+            buf_writer.write_all(welcome.as_bytes()).unwrap();
+        } else {
+            buf_writer.write_all(b"~").unwrap();
+        }
+
+        // erases part of the line to the right of the cursor
+        buf_writer.write_all(b"\x1b[K").unwrap();
+        buf_writer.write_all(b"\r\n").unwrap();
+    }
+}
+
+/// Draws the gutter for the row at `filerow`, dimmed except for the cursor's row.
+fn editor_draw_line_number<B: Backend>(
+    buf_writer: &mut Vec<u8>,
+    editor: &Editor<B>,
+    filerow: usize,
+) {
+    let number = match editor.view.line_number(filerow) {
+        Some(number) => number,
+        None => return,
+    };
+    let color = if filerow == editor.view.cursor_y {
+        &editor.config.colors.normal
+    } else {
+        &editor.config.colors.line_number
+    };
+    let width = editor.view.gutter_width - 1;
+    write!(buf_writer, "\x1b[{color}m{number:>width$}\x1b[m ").unwrap();
+}
+
+/// Render columns covered by each search match in the row at `filerow`.
+fn editor_row_search_matches<B: Backend>(
+    editor: &Editor<B>,
+    filerow: usize,
+) -> Vec<(usize, usize, Highlight)> {
+    let pattern = match &editor.search_pattern {
+        Some(pattern) => pattern,
+        None => return Vec::new(),
+    };
+
+    let line = editor.buffer.line(filerow).unwrap_or_default();
+    let line = line.as_ref();
+    let cursor_index = cursorx_to_byte(line, editor.view.cursor_x);
+    let tab_stop = editor.buffer.tab_stop();
+
+    pattern
+        .find(line)
+        .into_iter()
+        .map(|(start, end)| {
+            let start_cx = byte_to_cursorx(line, start);
+            let end_cx = byte_to_cursorx(line, end);
+            let hl = if filerow == editor.view.cursor_y && start == cursor_index {
+                Highlight::CurrentMatch
+            } else {
+                Highlight::Match
+            };
+            (
+                cursorx_to_renderx(line, start_cx, tab_stop),
+                cursorx_to_renderx(line, end_cx, tab_stop),
+                hl,
+            )
+        })
+        .collect()
+}
+
+/// Render columns covered by the selection in the row at `filerow`.
+fn editor_row_selection<B: Backend>(editor: &Editor<B>, filerow: usize) -> Option<(usize, usize)> {
+    let ((start_x, start_y), (end_x, end_y)) = editor.view.selection()?;
+    if filerow < start_y || filerow > end_y {
+        return None;
+    }
+
+    let line = editor.buffer.line(filerow).unwrap_or_default();
+    let start = if filerow == start_y {
+        cursorx_to_renderx(&line, start_x, editor.buffer.tab_stop())
+    } else {
+        0
+    };
+    let end = if filerow == end_y {
+        cursorx_to_renderx(&line, end_x, editor.buffer.tab_stop())
+    } else {
+        usize::MAX
+    };
+    Some((start, end))
+}
+
+/// Draws the render columns from `first_col` up to `last_col` of the row at `filerow`.
+fn editor_draw_row<B: Backend>(
+    buf_writer: &mut Vec<u8>,
+    editor: &Editor<B>,
+    filerow: usize,
+    first_col: usize,
+    last_col: usize,
+) {
+    let row = match editor.buffer.row(filerow) {
+        Some(row) => row,
+        None => return,
+    };
+    let matches = editor_row_search_matches(editor, filerow);
+    let selection = editor_row_selection(editor, filerow);
+
+    let mut current_color: Option<(&str, bool)> = None;
+    let mut rx: usize = 0;
+    for (i, g) in row.render().grapheme_indices(true) {
+        let start = rx;
+        rx += grapheme_width(g);
+        if rx <= first_col {
+            continue;
+        }
+        if start >= last_col {
+            break;
+        }
+
+        if start < first_col || rx > last_col {
+            // a wide character cut in half by the edge of the screen
+            for _ in start.max(first_col)..rx.min(last_col) {
+                buf_writer.write_all(b" ").unwrap();
+            }
+            continue;
+        }
+
+        if is_drawn_inverted(g) {
+            // show control characters as an inverted symbol, ctrl-a as A and so on
+            let c = g.chars().next().unwrap();
+            let symbol = if (c as u32) <= 26 {
+                (b'@' + c as u8) as char
+            } else {
+                '?'
+            };
+            write!(buf_writer, "\x1b[7m{symbol}\x1b[m").unwrap();
+            current_color = None;
+            continue;
+        }
+
+        // search matches are drawn over the syntax colors
+        let hl = matches
+            .iter()
+            .find(|(match_start, match_end, _)| (*match_start..*match_end).contains(&start))
+            .map_or(row.hl()[i], |(_, _, hl)| *hl);
+        let color = editor.config.colors.highlight(hl);
+        // selected text is drawn inverted
+        let selected =
+            selection.is_some_and(|(sel_start, sel_end)| (sel_start..sel_end).contains(&start));
+        if current_color != Some((color, selected)) {
+            // reset first so a match's background doesn't carry over
+            write!(buf_writer, "\x1b[m\x1b[{color}m").unwrap();
+            if selected {
+                buf_writer.write_all(b"\x1b[7m").unwrap();
+            }
+            current_color = Some((color, selected));
+        }
+        buf_writer.write_all(g.as_bytes()).unwrap();
+    }
+
+    // back to the default colors
+    buf_writer.write_all(b"\x1b[m").unwrap();
+}
+
+fn editor_draw_status_bar<B: Backend>(buf_writer: &mut Vec<u8>, editor: &Editor<B>) {
+    // invert colors
+    buf_writer.write_all(b"\x1b[7m").unwrap();
+    // clear line
+    buf_writer.write_all(b"\x1b[2K").unwrap();
+
+    let buffer = &editor.buffer;
+    // the bars span the gutter too
+    let screen_cols = editor.view.gutter_width + editor.view.screen_cols;
+    let filename = if !buffer.filename().is_empty() {
+        truncate_to_width(buffer.filename(), 20)
+    } else {
+        NO_FILENAME
+    };
+
+    // more lines are on the way while a mapped file is being indexed
+    let loading = if buffer.is_loading() { "+" } else { "" };
+    let num_of_lines = format!("{}{loading}", buffer.num_rows());
+    let status = format!("{filename} - {num_of_lines} lines");
+    let filetype = match buffer.syntax() {
+        Some(syntax) => syntax.filetype,
+        None => "no ft",
+    };
+    let line_ending = buffer.line_ending().name();
+    let line_pos = format!(
+        "{filetype} | {line_ending} | {}/{}",
+        editor.view.cursor_y + 1,
+        num_of_lines
+    );
+    let modified = if buffer.is_dirty() { " (modified)" } else { "" };
+
+    // on a narrow screen the right side is dropped first, then the left side is cut off
+    let status = format!("{status}{modified}");
+    let status = truncate_to_width(&status, screen_cols);
+    let status_width = str_width(status);
+    buf_writer.write_all(status.as_bytes()).unwrap();
+    if status_width + line_pos.len() <= screen_cols {
+        for _ in 0..screen_cols - status_width - line_pos.len() {
+            buf_writer.write_all(b" ").unwrap();
+        }
+        buf_writer.write_all(line_pos.as_bytes()).unwrap();
+    }
+
+    // newline
+    buf_writer.write_all(b"\r\n").unwrap();
+
+    // revert colors
+    buf_writer.write_all(b"\x1b[m").unwrap();
+}
+
+fn editor_draw_message_bar<B: Backend>(buf_writer: &mut Vec<u8>, editor: &Editor<B>) {
+    // clear line
+    buf_writer.write_all(b"\x1b[2K").unwrap();
+
+    if !editor.pending_keys.is_empty() {
+        editor_draw_chord_hint(buf_writer, editor);
+        return;
+    }
+
+    if SystemTime::now()
+        .duration_since(editor.status_message_time)
+        .unwrap()
+        < editor.config.message_timeout
+    {
+        let screen_cols = editor.view.gutter_width + editor.view.screen_cols;
+        let message = truncate_to_width(&editor.status_message, screen_cols);
+        buf_writer.write_all(message.as_bytes()).unwrap();
+    }
+}
+
+/// Shows the keys of the chord typed so far and what the keys that can follow them do.
+fn editor_draw_chord_hint<B: Backend>(buf_writer: &mut Vec<u8>, editor: &Editor<B>) {
+    let keys = &editor.pending_keys;
+    let next: Vec<String> = editor
+        .config
+        .keymap
+        .chords_after(keys)
+        .map(|(rest, command)| format!("{} = {command}", keys_name(rest)))
+        .collect();
+    let hint = format!("{}-  {}", keys_name(keys), next.join(" | "));
+
+    let screen_cols = editor.view.gutter_width + editor.view.screen_cols;
+    let hint = truncate_to_width(&hint, screen_cols);
+    buf_writer.write_all(hint.as_bytes()).unwrap();
+}
@@ -0,0 +1,418 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::os::unix::fs::{fchown, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str;
+
+// bytes that aren't valid utf-8 are kept as the private use characters U+10FE80..=U+10FEFF
+const RAW_BYTE_BASE: u32 = 0x10_FE00;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+        }
+    }
+
+    pub(crate) fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+/// The lines of a file along with how they were terminated.
+pub(crate) struct Decoded {
+    pub(crate) lines: Vec<String>,
+    pub(crate) line_ending: LineEnding,
+    pub(crate) final_newline: bool, // if the last line was terminated too
+}
+
+/// Splits the contents of a file into lines. Carriage returns are only taken as part of the
+/// line ending when every line has one, so files with mixed line endings save unchanged.
+pub(crate) fn decode(bytes: &[u8]) -> Decoded {
+    let mut lines: Vec<&[u8]> = bytes.split(|b| *b == b'\n').collect();
+    // split leaves an empty piece after the final newline, or the unterminated last line
+    let last = lines.pop().unwrap_or_default();
+    let final_newline = last.is_empty();
+    if !last.is_empty() {
+        lines.push(last);
+    }
+
+    let terminated = if final_newline {
+        lines.len()
+    } else {
+        lines.len() - 1
+    };
+    let crlf = terminated > 0 && lines[..terminated].iter().all(|line| line.ends_with(b"\r"));
+
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| match line.strip_suffix(b"\r") {
+            Some(stripped) if crlf && i < terminated => decode_line(stripped),
+            _ => decode_line(line),
+        })
+        .collect();
+
+    Decoded {
+        lines,
+        line_ending: if crlf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        },
+        final_newline,
+    }
+}
+
+/// Decodes `bytes` as utf-8, keeping every byte that isn't part of a valid sequence as its
+/// own raw byte character so the line is written back exactly as it was read.
+pub(crate) fn decode_line(mut bytes: &[u8]) -> String {
+    let mut line = String::with_capacity(bytes.len());
+    loop {
+        let (valid, invalid) = match str::from_utf8(bytes) {
+            Ok(valid) => (valid, &[][..]),
+            Err(e) => {
+                let invalid_len = e.error_len().unwrap_or(bytes.len() - e.valid_up_to());
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                let (invalid, rest) = rest.split_at(invalid_len);
+                bytes = rest;
+                // valid_up_to guarantees the prefix is utf-8
+                (str::from_utf8(valid).unwrap(), invalid)
+            }
+        };
+
+        for c in valid.chars() {
+            if raw_byte(c).is_some() {
+                // a genuine raw byte character would be saved as one byte, so keep its bytes
+                let mut encoded = [0u8; 4];
+                line.extend(c.encode_utf8(&mut encoded).bytes().map(raw_byte_char));
+            } else {
+                line.push(c);
+            }
+        }
+        line.extend(invalid.iter().copied().map(raw_byte_char));
+
+        if invalid.is_empty() {
+            return line;
+        }
+    }
+}
+
+fn raw_byte_char(b: u8) -> char {
+    char::from_u32(RAW_BYTE_BASE + b as u32).unwrap()
+}
+
+/// The undecodable byte that `c` stands in for, if any.
+pub fn raw_byte(c: char) -> Option<u8> {
+    (c as u32)
+        .checked_sub(RAW_BYTE_BASE)
+        .filter(|b| (0x80..=0xff).contains(b))
+        .map(|b| b as u8)
+}
+
+/// Appends `line` to `buf` as utf-8, with raw byte characters turned back into their bytes.
+pub(crate) fn encode_line(line: &str, buf: &mut Vec<u8>) {
+    for c in line.chars() {
+        match raw_byte(c) {
+            Some(b) => buf.push(b),
+            None => buf.extend_from_slice(c.encode_utf8(&mut [0u8; 4]).as_bytes()),
+        }
+    }
+}
+
+/// Writes the file's contents to the writer it is given.
+pub(crate) type Contents<'a> = &'a dyn Fn(&mut dyn Write) -> io::Result<()>;
+
+/// Writes `contents` to `filename` without ever leaving a partially written file behind:
+/// the contents go to a temp file in the same directory, which is synced and then
+/// renamed over the original. Saving through a symlink replaces the file it points to.
+/// Returns how many bytes were written.
+///
+/// Fails with `PermissionDenied` when the directory can't be written to, or when the new
+/// file couldn't be given the original's owner and group. `overwrite_file` still works then.
+pub(crate) fn write_file(filename: &str, contents: Contents) -> io::Result<u64> {
+    let path = match fs::canonicalize(filename) {
+        Ok(path) => path,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => PathBuf::from(filename),
+        Err(e) => return Err(e),
+    };
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    if metadata.is_some() {
+        // renaming over a read-only file would succeed, so ask for write access explicitly
+        OpenOptions::new().write(true).open(&path)?;
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let mode = metadata
+        .as_ref()
+        .map_or(0o644, |m| m.permissions().mode() & 0o7777);
+
+    let (tmp_path, file) = create_temp_file(dir, &name, mode)?;
+    let written = finish_temp_file(file, metadata.as_ref(), contents)
+        .and_then(|written| fs::rename(&tmp_path, &path).map(|_| written));
+    let written = match written {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+    };
+
+    // the rename only survives a crash once the directory entry is on disk too
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(written)
+}
+
+/// Overwrites the existing file `filename` with `contents`, keeping its owner and
+/// permissions. Unlike `write_file` this isn't crash-safe, the file is truncated before the
+/// new contents are written.
+pub(crate) fn overwrite_file(filename: &str, contents: Contents) -> io::Result<u64> {
+    let file = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(filename)?;
+    let written = write_contents(&file, contents)?;
+    file.sync_all()?;
+    Ok(written)
+}
+
+/// If `filename` is an existing file that can be written to.
+pub(crate) fn is_writable(filename: &str) -> bool {
+    OpenOptions::new().write(true).open(filename).is_ok()
+}
+
+fn create_temp_file(dir: &Path, name: &str, mode: u32) -> io::Result<(PathBuf, File)> {
+    let mut attempt = 0;
+    loop {
+        let tmp_path = dir.join(format!(".{}.{}-{}.tmp", name, process::id(), attempt));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .open(&tmp_path)
+        {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Fills the temp file and gives it the original file's permissions and owner, which the
+/// umask and the current user would otherwise override. Only root can give a file to
+/// someone else, so saving another user's file this way fails rather than take it over.
+fn finish_temp_file(
+    file: File,
+    metadata: Option<&fs::Metadata>,
+    contents: Contents,
+) -> io::Result<u64> {
+    let written = write_contents(&file, contents)?;
+    if let Some(metadata) = metadata {
+        let current = file.metadata()?;
+        if current.uid() != metadata.uid() || current.gid() != metadata.gid() {
+            fchown(&file, Some(metadata.uid()), Some(metadata.gid())).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "the file's owner can't be kept",
+                )
+            })?;
+        }
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.sync_all()?;
+    Ok(written)
+}
+
+/// Writes `contents` to `file` through a buffer and returns how many bytes that was.
+fn write_contents(file: &File, contents: Contents) -> io::Result<u64> {
+    let mut out = CountingWriter {
+        inner: BufWriter::new(file),
+        count: 0,
+    };
+    contents(&mut out)?;
+    out.flush()?;
+    Ok(out.count)
+}
+
+struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64, // bytes written so far
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes decoded lines back the way a buffer saves them.
+    fn encode(decoded: &Decoded) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for (i, line) in decoded.lines.iter().enumerate() {
+            encode_line(line, &mut buf);
+            if i + 1 < decoded.lines.len() || decoded.final_newline {
+                buf.extend_from_slice(decoded.line_ending.as_bytes());
+            }
+        }
+        buf
+    }
+
+    fn assert_round_trip(bytes: &[u8]) -> Decoded {
+        let decoded = decode(bytes);
+        assert_eq!(
+            encode(&decoded),
+            bytes,
+            "{:?}",
+            String::from_utf8_lossy(bytes)
+        );
+        decoded
+    }
+
+    #[test]
+    fn lf() {
+        let decoded = assert_round_trip(b"one\ntwo\n");
+        assert_eq!(decoded.lines, ["one", "two"]);
+        assert_eq!(decoded.line_ending, LineEnding::Lf);
+        assert!(decoded.final_newline);
+    }
+
+    #[test]
+    fn crlf() {
+        let decoded = assert_round_trip(b"one\r\ntwo\r\n");
+        assert_eq!(decoded.lines, ["one", "two"]);
+        assert_eq!(decoded.line_ending, LineEnding::CrLf);
+    }
+
+    #[test]
+    fn mixed_endings_keep_their_carriage_returns() {
+        let decoded = assert_round_trip(b"one\r\ntwo\nthree\r\n");
+        assert_eq!(decoded.lines, ["one\r", "two", "three\r"]);
+        assert_eq!(decoded.line_ending, LineEnding::Lf);
+    }
+
+    #[test]
+    fn missing_final_newline() {
+        let decoded = assert_round_trip(b"one\ntwo");
+        assert_eq!(decoded.lines, ["one", "two"]);
+        assert!(!decoded.final_newline);
+
+        let decoded = assert_round_trip(b"one\r\ntwo\r");
+        assert_eq!(decoded.lines, ["one", "two\r"]);
+        assert_eq!(decoded.line_ending, LineEnding::CrLf);
+    }
+
+    #[test]
+    fn empty_and_blank_files() {
+        let decoded = assert_round_trip(b"");
+        assert!(decoded.lines.is_empty());
+        assert_round_trip(b"\n");
+        assert_round_trip(b"\n\n");
+        assert_round_trip(b"\r\n");
+        assert_round_trip(b"\r");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let decoded = assert_round_trip(b"caf\xe9\n\xff\xfe\n\xe2\x82\n");
+        assert_eq!(
+            decoded.lines[0].chars().last().and_then(raw_byte),
+            Some(0xe9)
+        );
+        assert_eq!(decoded.lines[1].chars().count(), 2);
+        assert_eq!(decoded.lines[2].chars().filter_map(raw_byte).count(), 2);
+        assert_round_trip("€ \u{1f600}\n".as_bytes());
+    }
+
+    #[test]
+    fn literal_raw_byte_characters() {
+        let mut bytes = Vec::new();
+        for c in ['\u{10fe80}', '\u{10feab}', '\u{10feff}', '\u{10fe7f}'] {
+            bytes.extend_from_slice(c.to_string().as_bytes());
+            bytes.push(b'\n');
+        }
+        let decoded = assert_round_trip(&bytes);
+        // the characters can't be kept as themselves, they'd be saved as a single byte
+        assert!(decoded.lines[..3]
+            .iter()
+            .all(|line| line.chars().count() == 4));
+        assert_eq!(decoded.lines[3], "\u{10fe7f}");
+    }
+
+    #[test]
+    fn write_and_overwrite_keep_the_mode() {
+        let dir = std::env::temp_dir().join(format!("ronto-file-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        let filename = path.to_str().unwrap();
+        fs::write(&path, b"old contents\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let written = write_file(filename, &|out| out.write_all(b"new\n")).unwrap();
+        assert_eq!(written, 4);
+        assert_eq!(fs::read(&path).unwrap(), b"new\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        // the temp file was renamed away
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let written = overwrite_file(filename, &|out| out.write_all(b"in place")).unwrap();
+        assert_eq!(written, 8);
+        assert_eq!(fs::read(&path).unwrap(), b"in place");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
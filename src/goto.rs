@@ -0,0 +1,150 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::buffer::Buffer;
+use crate::row::line_len;
+
+/// The line part of a goto target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GotoLine {
+    Absolute(usize), // counting from 1
+    Relative(isize), // lines below the cursor, above it if negative
+    Percent(usize),  // how far through the file
+}
+
+/// Where goto moves the cursor to, typed as `120`, `120:5`, `+10`, `-10` or `50%`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GotoTarget {
+    pub line: GotoLine,
+    pub col: Option<usize>, // counting from 1, the start of the line if None
+}
+
+impl GotoTarget {
+    pub fn parse(text: &str) -> Option<GotoTarget> {
+        let text = text.trim();
+        let (line, col) = match text.split_once(':') {
+            Some((line, col)) => (line, Some(col.parse().ok()?)),
+            None => (text, None),
+        };
+
+        let line = if let Some(percent) = line.strip_suffix('%') {
+            GotoLine::Percent(percent.parse().ok()?)
+        } else if let Some(lines) = line.strip_prefix('+') {
+            GotoLine::Relative(lines.parse().ok()?)
+        } else if line.starts_with('-') {
+            GotoLine::Relative(line.parse().ok()?)
+        } else {
+            GotoLine::Absolute(line.parse().ok()?)
+        };
+        Some(GotoTarget { line, col })
+    }
+
+    /// The cursor position in `buffer` this points at, kept inside the buffer's rows and the
+    /// length of the row. `cursor_y` is where relative lines count from. In a file that is
+    /// still loading this waits for the row to be found, but a percentage is only of the rows
+    /// found so far.
+    pub fn resolve(&self, buffer: &Buffer, cursor_y: usize) -> (usize, usize) {
+        let y = match self.line {
+            GotoLine::Absolute(line) => line.saturating_sub(1),
+            GotoLine::Relative(lines) => cursor_y.saturating_add_signed(lines),
+            GotoLine::Percent(percent) => {
+                let line = buffer.num_rows().saturating_mul(percent).div_ceil(100);
+                line.saturating_sub(1)
+            }
+        };
+        buffer.wait_for_row(y);
+        let y = y.min(buffer.num_rows().saturating_sub(1));
+
+        let len = buffer.line(y).map_or(0, |line| line_len(&line));
+        let x = self.col.map_or(0, |col| col.saturating_sub(1)).min(len);
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(line: GotoLine, col: Option<usize>) -> Option<GotoTarget> {
+        Some(GotoTarget { line, col })
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            GotoTarget::parse("120"),
+            target(GotoLine::Absolute(120), None)
+        );
+        assert_eq!(
+            GotoTarget::parse(" 7 "),
+            target(GotoLine::Absolute(7), None)
+        );
+        assert_eq!(
+            GotoTarget::parse("+10"),
+            target(GotoLine::Relative(10), None)
+        );
+        assert_eq!(
+            GotoTarget::parse("-10"),
+            target(GotoLine::Relative(-10), None)
+        );
+        assert_eq!(
+            GotoTarget::parse("50%"),
+            target(GotoLine::Percent(50), None)
+        );
+        assert_eq!(
+            GotoTarget::parse("120:5"),
+            target(GotoLine::Absolute(120), Some(5))
+        );
+        assert_eq!(
+            GotoTarget::parse("-3:2"),
+            target(GotoLine::Relative(-3), Some(2))
+        );
+
+        for bad in [
+            "", "abc", "12a", "1.5", "+", "-", "%", "-5%", "10:", "10:x", ":5",
+        ] {
+            assert_eq!(GotoTarget::parse(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn resolve_stays_inside_the_buffer() {
+        let mut buffer = Buffer::new();
+        let lines: Vec<String> = (1..=10).map(|i| format!("line {i}")).collect();
+        buffer.insert_text((0, 0), &lines.join("\n"));
+
+        let resolve = |text: &str, cursor_y: usize| {
+            GotoTarget::parse(text).unwrap().resolve(&buffer, cursor_y)
+        };
+        assert_eq!(resolve("3", 0), (0, 2));
+        assert_eq!(resolve("3:4", 0), (3, 2));
+        assert_eq!(resolve("3:99", 0), (6, 2));
+        assert_eq!(resolve("0", 5), (0, 0));
+        assert_eq!(resolve("99", 0), (0, 9));
+        assert_eq!(resolve("+2", 5), (0, 7));
+        assert_eq!(resolve("-9", 5), (0, 0));
+        assert_eq!(resolve("+9", 5), (0, 9));
+        assert_eq!(resolve("50%", 0), (0, 4));
+        assert_eq!(resolve("0%", 5), (0, 0));
+        assert_eq!(resolve("100%", 0), (0, 9));
+        assert_eq!(resolve("500%", 0), (0, 9));
+    }
+}
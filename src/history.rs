@@ -0,0 +1,168 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::file::LineEnding;
+use crate::syntax::is_separator_char;
+
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    pub(crate) undo_stack: Vec<EditGroup>,
+    pub(crate) redo_stack: Vec<EditGroup>,
+    pub(crate) saved_depth: Option<usize>, // undo_stack length when the file was last saved
+    pub(crate) sealed: bool,               // if the next edit has to start a new group
+    open_edits: usize,                     // row changes are recorded while this is above 0
+}
+
+/// Row changes that are undone and redone together, along with where the cursor was before
+/// and after them.
+#[derive(Debug)]
+pub(crate) struct EditGroup {
+    kind: EditKind,
+    pub(crate) changes: Vec<RowChange>,
+    pub(crate) cursor_before: (usize, usize),
+    pub(crate) cursor_after: (usize, usize),
+}
+
+/// What an edit does, used to decide whether it is undone together with the edit before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    Insert(char), // last character typed
+    Delete(char), // last character deleted
+    Other,
+}
+
+/// A change to the rows. Rows inserted or deleted together are recorded as one change, so
+/// that undoing a big paste or deletion doesn't touch them one at a time.
+#[derive(Debug)]
+pub(crate) enum RowChange {
+    Insert { at: usize, lines: Vec<String> },
+    Delete { at: usize, lines: Vec<String> },
+    Replace { at: usize, old: String, new: String },
+    LineEnding { old: Option<LineEnding>, new: Option<LineEnding> },
+}
+
+impl History {
+    /// A history for a buffer that matches what is on disk.
+    pub(crate) fn new() -> History {
+        History {
+            saved_depth: Some(0),
+            ..Default::default()
+        }
+    }
+
+    /// Opens a group for the edit that is about to happen. Typing and deleting keep adding to
+    /// the previous group until a word boundary is crossed or the cursor is moved in between.
+    /// Edits begun while another one is open become part of it.
+    pub(crate) fn begin(&mut self, kind: EditKind, cursor: (usize, usize)) {
+        self.open_edits += 1;
+        if self.open_edits > 1 {
+            return;
+        }
+
+        let coalesce = !self.sealed
+            && self.undo_stack.last().is_some_and(|group| {
+                group.cursor_after == cursor && edits_coalesce(group.kind, kind)
+            });
+
+        if !coalesce {
+            self.redo_stack.clear();
+            // the saved state can't be reached again once it has been undone and edited over
+            if self
+                .saved_depth
+                .is_some_and(|depth| depth > self.undo_stack.len())
+            {
+                self.saved_depth = None;
+            }
+            self.undo_stack.push(EditGroup {
+                kind,
+                changes: Vec::new(),
+                cursor_before: cursor,
+                cursor_after: cursor,
+            });
+        }
+
+        if let Some(group) = self.undo_stack.last_mut() {
+            group.kind = kind;
+        }
+        self.sealed = false;
+    }
+
+    pub(crate) fn end(&mut self, cursor: (usize, usize)) {
+        self.open_edits = self.open_edits.saturating_sub(1);
+        if self.open_edits > 0 {
+            return;
+        }
+
+        if let Some(group) = self.undo_stack.last_mut() {
+            group.cursor_after = cursor;
+            if group.changes.is_empty() {
+                self.undo_stack.pop();
+            }
+        }
+    }
+
+    pub(crate) fn record(&mut self, change: RowChange) {
+        if self.open_edits == 0 {
+            return;
+        }
+
+        let group = match self.undo_stack.last_mut() {
+            Some(group) => group,
+            None => return,
+        };
+
+        // repeated changes to one row only need its first and latest contents
+        if let RowChange::Replace { at, new, .. } = &change {
+            if let Some(RowChange::Replace {
+                at: last_at,
+                new: last_new,
+                ..
+            }) = group.changes.last_mut()
+            {
+                if at == last_at {
+                    *last_new = new.clone();
+                    return;
+                }
+            }
+        }
+
+        group.changes.push(change);
+    }
+
+    pub(crate) fn mark_saved(&mut self) {
+        self.saved_depth = Some(self.undo_stack.len());
+        self.sealed = true;
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.saved_depth != Some(self.undo_stack.len())
+    }
+}
+
+fn edits_coalesce(prev: EditKind, next: EditKind) -> bool {
+    match (prev, next) {
+        // typing the first letter of a word starts a new group
+        (EditKind::Insert(p), EditKind::Insert(c)) => !is_separator_char(p) || is_separator_char(c),
+        // deleting past the start of a word does too
+        (EditKind::Delete(p), EditKind::Delete(c)) => is_separator_char(p) || !is_separator_char(c),
+        _ => false,
+    }
+}
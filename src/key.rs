@@ -0,0 +1,818 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use core::str;
+use std::fmt;
+
+/// A key, without the modifiers held with it. Letters typed with Ctrl are reported as the
+/// lowercase letter with `Modifiers::ctrl` set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    F(u8), // function keys, F(1) to F(12)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        alt: false,
+        ctrl: false,
+    };
+    pub const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..Modifiers::NONE
+    };
+    pub const ALT: Modifiers = Modifiers {
+        alt: true,
+        ..Modifiers::NONE
+    };
+    pub const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        ..Modifiers::NONE
+    };
+
+    /// Decodes the modifier parameter of a CSI sequence, which is 1 plus a bit mask of
+    /// shift (1), alt (2), ctrl (4) and meta (8). Meta is taken as alt.
+    fn from_param(param: u32) -> Modifiers {
+        let bits = param.saturating_sub(1);
+        Modifiers {
+            shift: bits & 1 != 0,
+            alt: bits & 2 != 0 || bits & 8 != 0,
+            ctrl: bits & 4 != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    pub const fn new(code: KeyCode, modifiers: Modifiers) -> KeyEvent {
+        KeyEvent { code, modifiers }
+    }
+
+    pub const fn plain(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, Modifiers::NONE)
+    }
+
+    /// Ctrl held with a letter, `c` must be lowercase.
+    pub const fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), Modifiers::CTRL)
+    }
+
+    pub const fn alt(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), Modifiers::ALT)
+    }
+
+    /// Parses a key written the way `Display` writes it, such as `ctrl-s`, `alt-w`,
+    /// `shift-up`, `pagedown`, `f5` or `space`. Case only matters for a character typed on its
+    /// own.
+    pub fn from_name(name: &str) -> Option<KeyEvent> {
+        let mut name = name;
+        let mut modifiers = Modifiers::NONE;
+        // "ctrl--" is ctrl with the minus key
+        while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty())
+        {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                _ => return None,
+            }
+            name = rest;
+        }
+
+        let code = match name.to_ascii_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "esc" => KeyCode::Esc,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "insert" => KeyCode::Insert,
+            "delete" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            lower => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    // letters typed with ctrl are always lowercase
+                    (Some(c), None) if modifiers.ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match lower.strip_prefix('f').map(str::parse) {
+                        Some(Ok(n @ 1..=12)) => KeyCode::F(n),
+                        _ => return None,
+                    },
+                }
+            }
+        };
+        Some(KeyEvent::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            f.write_str("Ctrl-")?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt-")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) if self.modifiers.ctrl => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseKind {
+    Press(MouseButton),
+    Release(MouseButton),
+    Drag(MouseButton), // moved with the button held down
+    ScrollUp,
+    ScrollDown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub kind: MouseKind,
+    pub row: usize, // screen position, starting at 0
+    pub col: usize,
+    pub modifiers: Modifiers,
+}
+
+/// Something the editor has to react to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Paste(String), // text pasted in one go, with line breaks as '\n'
+    Resize,        // the screen changed size
+}
+
+/// What `decode` made of the start of the input.
+enum Decoded {
+    Key(KeyEvent, usize),     // a key and how many bytes it took
+    Mouse(MouseEvent, usize), // same for a mouse event
+    Skip(usize),              // bytes that aren't a key we know
+    Incomplete,               // could be the start of a longer sequence, wait for more
+}
+
+// terminals in bracketed paste mode send pasted text between these
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Turns the bytes read from a terminal into keys and pastes. Input is buffered until it makes
+/// up a whole key, so a sequence split across reads and several keys arriving in one read both
+/// decode correctly.
+#[derive(Debug, Default)]
+pub struct KeyDecoder {
+    pending: Vec<u8>,      // bytes read but not decoded yet
+    paste_searched: usize, // how far a paste in `pending` has been searched for its end
+}
+
+impl KeyDecoder {
+    pub fn new() -> KeyDecoder {
+        KeyDecoder::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// If a paste has started and its end hasn't been read yet.
+    pub fn in_paste(&self) -> bool {
+        self.pending.starts_with(PASTE_START)
+    }
+
+    /// Decodes the next key or paste. Returns None once everything is decoded, or when what is
+    /// left could be the start of a longer sequence. A terminal sends a whole sequence at once,
+    /// so if nothing more came in after a short wait call this again with `timed_out` set and
+    /// the rest is taken as it is: a lone ESC is the Esc key and ESC followed by a key is that
+    /// key with Alt. A paste that times out before its end marker ends with what came in, so
+    /// a lost marker doesn't take all later input as pasted text.
+    pub fn next_event(&mut self, timed_out: bool) -> Option<Event> {
+        while !self.pending.is_empty() {
+            if self.in_paste() {
+                return self.next_paste(timed_out);
+            }
+            match decode(&self.pending, timed_out) {
+                Decoded::Key(key, len) => {
+                    self.pending.drain(..len);
+                    return Some(Event::Key(key));
+                }
+                Decoded::Mouse(mouse, len) => {
+                    self.pending.drain(..len);
+                    return Some(Event::Mouse(mouse));
+                }
+                Decoded::Skip(len) => {
+                    self.pending.drain(..len);
+                }
+                Decoded::Incomplete => return None,
+            }
+        }
+        None
+    }
+
+    /// Takes the paste `pending` starts with, if all of it has been read or `timed_out` is
+    /// set.
+    fn next_paste(&mut self, timed_out: bool) -> Option<Event> {
+        // a big paste comes in over many reads, only what's new is searched each time
+        let from = self.paste_searched.max(PASTE_START.len());
+        let found = self.pending[from..]
+            .windows(PASTE_END.len())
+            .position(|w| w == PASTE_END);
+        let (end, marker_len) = match found {
+            Some(i) => (from + i, PASTE_END.len()),
+            None if timed_out => {
+                // the end marker may have been cut short along with the paste
+                let body = &self.pending[PASTE_START.len()..];
+                let partial = (1..PASTE_END.len())
+                    .rev()
+                    .find(|n| body.ends_with(&PASTE_END[..*n]))
+                    .unwrap_or(0);
+                (self.pending.len() - partial, partial)
+            }
+            None => {
+                let searched = self.pending.len().saturating_sub(PASTE_END.len() - 1);
+                self.paste_searched = searched.max(from);
+                return None;
+            }
+        };
+
+        // terminals send line breaks as they would the Enter key
+        let text = String::from_utf8_lossy(&self.pending[PASTE_START.len()..end])
+            .replace("\r\n", "\n")
+            .replace('\r', "\n");
+        self.pending.drain(..end + marker_len);
+        self.paste_searched = 0;
+        Some(Event::Paste(text))
+    }
+}
+
+/// Decodes the key at the start of `bytes`, which isn't empty. With `timed_out` set this
+/// never returns `Incomplete`.
+fn decode(bytes: &[u8], timed_out: bool) -> Decoded {
+    let key = |code, len| Decoded::Key(KeyEvent::plain(code), len);
+    match bytes[0] {
+        0x1b => decode_escape(bytes, timed_out),
+        b'\r' => key(KeyCode::Enter, 1),
+        b'\t' => key(KeyCode::Tab, 1),
+        0x7f => key(KeyCode::Backspace, 1),
+        0x00 => Decoded::Key(KeyEvent::ctrl(' '), 1),
+        // ctrl clears the top bits of the key that was typed with it
+        b @ 0x01..=0x1a => Decoded::Key(KeyEvent::ctrl((b | 0x60) as char), 1),
+        b @ 0x1c..=0x1f => Decoded::Key(KeyEvent::ctrl((b | 0x40) as char), 1),
+        b if b.is_ascii() => key(KeyCode::Char(b as char), 1),
+        _ => decode_utf8(bytes, timed_out),
+    }
+}
+
+fn decode_utf8(bytes: &[u8], timed_out: bool) -> Decoded {
+    let width = match bytes[0] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Decoded::Skip(1),
+    };
+    if bytes.len() < width {
+        let continues = bytes[1..].iter().all(|b| b & 0xc0 == 0x80);
+        if continues && !timed_out {
+            return Decoded::Incomplete;
+        }
+        return Decoded::Skip(1);
+    }
+
+    match str::from_utf8(&bytes[..width])
+        .ok()
+        .and_then(|s| s.chars().next())
+    {
+        Some(c) => Decoded::Key(KeyEvent::plain(KeyCode::Char(c)), width),
+        None => Decoded::Skip(1),
+    }
+}
+
+fn decode_escape(bytes: &[u8], timed_out: bool) -> Decoded {
+    // the end of a sequence, None if it hasn't all been read yet
+    let end = |from: usize, is_param: fn(&u8) -> bool| {
+        bytes[from..]
+            .iter()
+            .position(|b| !is_param(b))
+            .map(|i| from + i)
+    };
+    let incomplete = |len| {
+        if timed_out {
+            Decoded::Skip(len)
+        } else {
+            Decoded::Incomplete
+        }
+    };
+
+    match bytes.get(1) {
+        None if timed_out => Decoded::Key(KeyEvent::plain(KeyCode::Esc), 1),
+        None => Decoded::Incomplete,
+        // ESC [ or ESC O with nothing after it is Alt-[ or Alt-O
+        Some(b'[') | Some(b'O') if bytes.len() == 2 && timed_out => {
+            Decoded::Key(KeyEvent::alt(bytes[1] as char), 2)
+        }
+        Some(b'[') => match end(2, |b| (0x20..=0x3f).contains(b)) {
+            Some(end) if (0x40..=0x7e).contains(&bytes[end]) => decode_csi(&bytes[2..=end]),
+            Some(end) => Decoded::Skip(end), // cut short by something that isn't part of it
+            None => incomplete(bytes.len()),
+        },
+        Some(b'O') => match end(2, u8::is_ascii_digit) {
+            Some(end) if (0x40..=0x7e).contains(&bytes[end]) => decode_ss3(&bytes[2..=end]),
+            Some(end) => Decoded::Skip(end),
+            None => incomplete(bytes.len()),
+        },
+        Some(_) => match decode(&bytes[1..], timed_out) {
+            Decoded::Key(mut key, len) => {
+                key.modifiers.alt = true;
+                Decoded::Key(key, len + 1)
+            }
+            Decoded::Mouse(mut mouse, len) => {
+                mouse.modifiers.alt = true;
+                Decoded::Mouse(mouse, len + 1)
+            }
+            Decoded::Skip(len) => Decoded::Skip(len + 1),
+            Decoded::Incomplete => Decoded::Incomplete,
+        },
+    }
+}
+
+/// Decodes the parameters and final byte of a CSI sequence, `ESC [` not included.
+fn decode_csi(seq: &[u8]) -> Decoded {
+    let len = seq.len() + 2;
+    let (last, params) = seq.split_last().unwrap();
+    if let Some(params) = params.strip_prefix(b"<") {
+        return decode_mouse(params, *last, len);
+    }
+    let params: Vec<u32> = str::from_utf8(params)
+        .unwrap_or("")
+        .split(';')
+        .map(|p| p.parse().unwrap_or(0))
+        .collect();
+    let param = |n: usize| params.get(n).copied().unwrap_or(0);
+    let modifiers = Modifiers::from_param(param(1));
+
+    let code = match *last {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P' => KeyCode::F(1),
+        b'Q' => KeyCode::F(2),
+        b'R' => KeyCode::F(3),
+        b'S' => KeyCode::F(4),
+        b'Z' => return Decoded::Key(KeyEvent::new(KeyCode::Tab, Modifiers::SHIFT), len),
+        b'~' => match param(0) {
+            1 | 7 => KeyCode::Home,
+            2 => KeyCode::Insert,
+            3 => KeyCode::Delete,
+            4 | 8 => KeyCode::End,
+            5 => KeyCode::PageUp,
+            6 => KeyCode::PageDown,
+            n @ 11..=15 => KeyCode::F((n - 10) as u8),
+            n @ 17..=21 => KeyCode::F((n - 11) as u8),
+            n @ 23..=24 => KeyCode::F((n - 12) as u8),
+            _ => return Decoded::Skip(len),
+        },
+        // keys sent as their code point, by terminals that report every key this way
+        b'u' => match param(0) {
+            9 => KeyCode::Tab,
+            13 => KeyCode::Enter,
+            27 => KeyCode::Esc,
+            127 => KeyCode::Backspace,
+            n => match char::from_u32(n) {
+                Some(c) => KeyCode::Char(c),
+                None => return Decoded::Skip(len),
+            },
+        },
+        _ => return Decoded::Skip(len),
+    };
+    Decoded::Key(KeyEvent::new(code, modifiers), len)
+}
+
+/// Decodes an SGR mouse report, `ESC [ <` and the final byte not included. The parameters
+/// are the button and modifiers, then the column and row starting at 1.
+fn decode_mouse(params: &[u8], last: u8, len: usize) -> Decoded {
+    let params: Vec<usize> = str::from_utf8(params)
+        .unwrap_or("")
+        .split(';')
+        .map(|p| p.parse().unwrap_or(0))
+        .collect();
+    let &[button, col, row] = &params[..] else {
+        return Decoded::Skip(len);
+    };
+    if last != b'M' && last != b'm' {
+        return Decoded::Skip(len);
+    }
+
+    // the low bits are the button, the others flag modifiers, motion and the wheel
+    let pressed = match button & 3 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        2 => MouseButton::Right,
+        _ => return Decoded::Skip(len),
+    };
+    let kind = if button & 0x40 != 0 {
+        match pressed {
+            MouseButton::Left => MouseKind::ScrollUp,
+            MouseButton::Middle => MouseKind::ScrollDown,
+            MouseButton::Right => return Decoded::Skip(len), // sideways scrolling
+        }
+    } else if button & 0x20 != 0 {
+        MouseKind::Drag(pressed)
+    } else if last == b'M' {
+        MouseKind::Press(pressed)
+    } else {
+        MouseKind::Release(pressed)
+    };
+
+    let mouse = MouseEvent {
+        kind,
+        row: row.saturating_sub(1),
+        col: col.saturating_sub(1),
+        modifiers: Modifiers {
+            shift: button & 4 != 0,
+            alt: button & 8 != 0,
+            ctrl: button & 16 != 0,
+        },
+    };
+    Decoded::Mouse(mouse, len)
+}
+
+/// Decodes an SS3 sequence, `ESC O` not included. Some terminals put a modifier parameter
+/// before the final byte.
+fn decode_ss3(seq: &[u8]) -> Decoded {
+    let len = seq.len() + 2;
+    let (last, param) = seq.split_last().unwrap();
+    let modifiers = Modifiers::from_param(str::from_utf8(param).unwrap().parse().unwrap_or(0));
+
+    let code = match *last {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P' => KeyCode::F(1),
+        b'Q' => KeyCode::F(2),
+        b'R' => KeyCode::F(3),
+        b'S' => KeyCode::F(4),
+        _ => return Decoded::Skip(len),
+    };
+    Decoded::Key(KeyEvent::new(code, modifiers), len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: Modifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    fn plain(code: KeyCode) -> Event {
+        key(code, Modifiers::NONE)
+    }
+
+    fn mouse(kind: MouseKind, row: usize, col: usize, modifiers: Modifiers) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            row,
+            col,
+            modifiers,
+        })
+    }
+
+    /// Decodes `bytes` read in one go, timing out once nothing more decodes.
+    fn decode_all(bytes: &[u8]) -> Vec<Event> {
+        let mut decoder = KeyDecoder::new();
+        decoder.push(bytes);
+        let mut events = Vec::new();
+        while let Some(event) = decoder
+            .next_event(false)
+            .or_else(|| decoder.next_event(true))
+        {
+            events.push(event);
+        }
+        assert!(decoder.is_empty());
+        events
+    }
+
+    #[test]
+    fn keys() {
+        let ctrl_shift = Modifiers {
+            shift: true,
+            ctrl: true,
+            ..Modifiers::NONE
+        };
+        let table: &[(&[u8], Event)] = &[
+            (b"a", plain(KeyCode::Char('a'))),
+            (b"\r", plain(KeyCode::Enter)),
+            (b"\t", plain(KeyCode::Tab)),
+            (b"\x7f", plain(KeyCode::Backspace)),
+            (b"\x13", Event::Key(KeyEvent::ctrl('s'))),
+            (b"\x00", Event::Key(KeyEvent::ctrl(' '))),
+            (b"\x1c", Event::Key(KeyEvent::ctrl('\\'))),
+            ("é".as_bytes(), plain(KeyCode::Char('é'))),
+            ("\u{1f600}".as_bytes(), plain(KeyCode::Char('\u{1f600}'))),
+            (b"\x1bx", Event::Key(KeyEvent::alt('x'))),
+            (b"\x1b\r", key(KeyCode::Enter, Modifiers::ALT)),
+            (b"\x1b[A", plain(KeyCode::Up)),
+            (b"\x1b[B", plain(KeyCode::Down)),
+            (b"\x1b[C", plain(KeyCode::Right)),
+            (b"\x1b[D", plain(KeyCode::Left)),
+            (b"\x1b[H", plain(KeyCode::Home)),
+            (b"\x1b[F", plain(KeyCode::End)),
+            (b"\x1b[1~", plain(KeyCode::Home)),
+            (b"\x1b[7~", plain(KeyCode::Home)),
+            (b"\x1b[2~", plain(KeyCode::Insert)),
+            (b"\x1b[3~", plain(KeyCode::Delete)),
+            (b"\x1b[4~", plain(KeyCode::End)),
+            (b"\x1b[5~", plain(KeyCode::PageUp)),
+            (b"\x1b[6~", plain(KeyCode::PageDown)),
+            (b"\x1b[Z", key(KeyCode::Tab, Modifiers::SHIFT)),
+            (b"\x1b[1;2A", key(KeyCode::Up, Modifiers::SHIFT)),
+            (b"\x1b[1;3D", key(KeyCode::Left, Modifiers::ALT)),
+            (b"\x1b[1;5C", key(KeyCode::Right, Modifiers::CTRL)),
+            (b"\x1b[1;6H", key(KeyCode::Home, ctrl_shift)),
+            (b"\x1b[1;9B", key(KeyCode::Down, Modifiers::ALT)),
+            (b"\x1b[3;5~", key(KeyCode::Delete, Modifiers::CTRL)),
+            (b"\x1bOA", plain(KeyCode::Up)),
+            (b"\x1bOH", plain(KeyCode::Home)),
+            (b"\x1bOP", plain(KeyCode::F(1))),
+            (b"\x1bOS", plain(KeyCode::F(4))),
+            (b"\x1bO5C", key(KeyCode::Right, Modifiers::CTRL)),
+            (b"\x1b[15~", plain(KeyCode::F(5))),
+            (b"\x1b[17~", plain(KeyCode::F(6))),
+            (b"\x1b[21~", plain(KeyCode::F(10))),
+            (b"\x1b[24;2~", key(KeyCode::F(12), Modifiers::SHIFT)),
+            (b"\x1b[1;5P", key(KeyCode::F(1), Modifiers::CTRL)),
+            (b"\x1b[97;5u", Event::Key(KeyEvent::ctrl('a'))),
+            (b"\x1b[13;2u", key(KeyCode::Enter, Modifiers::SHIFT)),
+            (b"\x1b[27u", plain(KeyCode::Esc)),
+        ];
+        for (bytes, event) in table {
+            assert_eq!(decode_all(bytes), std::slice::from_ref(event), "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn mouse_reports() {
+        use MouseButton::*;
+        let table: &[(&[u8], Event)] = &[
+            (
+                b"\x1b[<0;1;1M",
+                mouse(MouseKind::Press(Left), 0, 0, Modifiers::NONE),
+            ),
+            (
+                b"\x1b[<0;10;5m",
+                mouse(MouseKind::Release(Left), 4, 9, Modifiers::NONE),
+            ),
+            (
+                b"\x1b[<1;3;2M",
+                mouse(MouseKind::Press(Middle), 1, 2, Modifiers::NONE),
+            ),
+            (
+                b"\x1b[<2;3;2M",
+                mouse(MouseKind::Press(Right), 1, 2, Modifiers::NONE),
+            ),
+            (
+                b"\x1b[<32;7;3M",
+                mouse(MouseKind::Drag(Left), 2, 6, Modifiers::NONE),
+            ),
+            (
+                b"\x1b[<64;1;1M",
+                mouse(MouseKind::ScrollUp, 0, 0, Modifiers::NONE),
+            ),
+            (
+                b"\x1b[<65;1;1M",
+                mouse(MouseKind::ScrollDown, 0, 0, Modifiers::NONE),
+            ),
+            (
+                b"\x1b[<4;2;2M",
+                mouse(MouseKind::Press(Left), 1, 1, Modifiers::SHIFT),
+            ),
+            (
+                b"\x1b[<16;2;2M",
+                mouse(MouseKind::Press(Left), 1, 1, Modifiers::CTRL),
+            ),
+            (
+                b"\x1b[<8;2;2M",
+                mouse(MouseKind::Press(Left), 1, 1, Modifiers::ALT),
+            ),
+        ];
+        for (bytes, event) in table {
+            assert_eq!(decode_all(bytes), std::slice::from_ref(event), "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn unknown_sequences_are_skipped() {
+        let table: &[&[u8]] = &[
+            b"\x1b[99~",
+            b"\x1b[x",
+            b"\x1bOz",
+            b"\x1b[<66;1;1M", // sideways scrolling
+            b"\x1b[<0;1M",
+            b"\xff",
+            b"\xe2\x82", // cut short
+        ];
+        for bytes in table {
+            assert_eq!(decode_all(bytes), [], "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn several_keys_in_one_read() {
+        assert_eq!(
+            decode_all(b"ab\x1b[A\x1b[<0;2;3M\r"),
+            [
+                plain(KeyCode::Char('a')),
+                plain(KeyCode::Char('b')),
+                plain(KeyCode::Up),
+                mouse(MouseKind::Press(MouseButton::Left), 2, 1, Modifiers::NONE),
+                plain(KeyCode::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn input_split_across_reads() {
+        let sequences: &[&[u8]] = &[
+            b"\x1b[1;5C",
+            b"\x1b[24~",
+            b"\x1bOP",
+            b"\x1b[<32;7;3M",
+            "\u{1f600}".as_bytes(),
+            b"\x1b[200~pasted\x1b[201~",
+        ];
+        for bytes in sequences {
+            let whole = decode_all(bytes);
+            for split in 1..bytes.len() {
+                let mut decoder = KeyDecoder::new();
+                decoder.push(&bytes[..split]);
+                assert_eq!(
+                    decoder.next_event(false),
+                    None,
+                    "{bytes:?} split at {split}"
+                );
+                decoder.push(&bytes[split..]);
+                assert_eq!(decoder.next_event(false).as_ref(), whole.first());
+                assert!(decoder.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn lone_esc_after_timeout() {
+        let mut decoder = KeyDecoder::new();
+        decoder.push(b"\x1b");
+        assert_eq!(decoder.next_event(false), None);
+        assert_eq!(decoder.next_event(true), Some(plain(KeyCode::Esc)));
+        assert!(decoder.is_empty());
+
+        // ESC then the start of a sequence that never finishes
+        decoder.push(b"\x1b[");
+        assert_eq!(decoder.next_event(false), None);
+        assert_eq!(
+            decoder.next_event(true),
+            Some(Event::Key(KeyEvent::alt('[')))
+        );
+
+        // the Esc key typed right before another key
+        decoder.push(b"\x1b");
+        assert_eq!(decoder.next_event(true), Some(plain(KeyCode::Esc)));
+        decoder.push(b"j");
+        assert_eq!(decoder.next_event(false), Some(plain(KeyCode::Char('j'))));
+    }
+
+    #[test]
+    fn pastes() {
+        assert_eq!(
+            decode_all(b"\x1b[200~one\r\ntwo\rthree\x1b[201~x"),
+            [
+                Event::Paste("one\ntwo\nthree".to_string()),
+                plain(KeyCode::Char('x')),
+            ]
+        );
+        // keys inside a paste are text
+        assert_eq!(
+            decode_all(b"\x1b[200~\x1b[A\x13\x1b[201~"),
+            [Event::Paste("\x1b[A\x13".to_string())]
+        );
+    }
+
+    #[test]
+    fn paste_without_end_marker() {
+        let mut decoder = KeyDecoder::new();
+        decoder.push(b"\x1b[200~lost");
+        assert!(decoder.in_paste());
+        assert_eq!(decoder.next_event(false), None);
+        decoder.push(b" end");
+        assert_eq!(decoder.next_event(false), None);
+        assert_eq!(
+            decoder.next_event(true),
+            Some(Event::Paste("lost end".to_string()))
+        );
+        assert!(decoder.is_empty());
+
+        // typing goes back to being keys
+        decoder.push(b"a");
+        assert_eq!(decoder.next_event(false), Some(plain(KeyCode::Char('a'))));
+
+        // the end marker cut short is dropped with it
+        decoder.push(b"\x1b[200~text\x1b[20");
+        assert_eq!(
+            decoder.next_event(true),
+            Some(Event::Paste("text".to_string()))
+        );
+        assert!(decoder.is_empty());
+
+        decoder.push(b"\x1b[200~");
+        assert_eq!(decoder.next_event(true), Some(Event::Paste(String::new())));
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn key_names() {
+        for name in [
+            "Ctrl-S", "Alt-W", "Shift-Up", "PageDown", "F5", "Space", "Ctrl--", "a",
+        ] {
+            let key = KeyEvent::from_name(name).unwrap();
+            assert_eq!(key.to_string(), name);
+        }
+        assert_eq!(KeyEvent::from_name("ctrl-S"), Some(KeyEvent::ctrl('s')));
+        assert_eq!(KeyEvent::from_name("f13"), None);
+        assert_eq!(KeyEvent::from_name("hyper-a"), None);
+    }
+}
@@ -0,0 +1,270 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::command::{Action, Command};
+use crate::key::{KeyCode, KeyEvent, Modifiers};
+
+/// What the keys pressed so far are bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    Command(Command),
+    Prefix, // the start of a chord, more keys are needed
+    Unbound,
+}
+
+/// Which command each key, or chord of several keys pressed one after the other, runs.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyEvent>, Command)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let key = KeyEvent::plain;
+        let bindings = [
+            (KeyEvent::ctrl('q'), Action::Quit),
+            (KeyEvent::ctrl('s'), Action::Save),
+            (KeyEvent::ctrl('f'), Action::Find),
+            (KeyEvent::ctrl('r'), Action::Replace),
+            (KeyEvent::ctrl('z'), Action::Undo),
+            (KeyEvent::ctrl('y'), Action::Redo),
+            (KeyEvent::ctrl('c'), Action::Copy),
+            (KeyEvent::ctrl('x'), Action::Cut),
+            (KeyEvent::ctrl('v'), Action::Paste),
+            (KeyEvent::ctrl('g'), Action::Goto),
+            (KeyEvent::ctrl('p'), Action::CommandPrompt),
+            (KeyEvent::alt(':'), Action::CommandPrompt),
+            (key(KeyCode::Enter), Action::NewLine),
+            (key(KeyCode::Tab), Action::Tab),
+            (key(KeyCode::Backspace), Action::DeleteBack),
+            (KeyEvent::ctrl('h'), Action::DeleteBack),
+            (key(KeyCode::Delete), Action::DeleteForward),
+            (key(KeyCode::Up), Action::Up),
+            (key(KeyCode::Down), Action::Down),
+            (key(KeyCode::Left), Action::Left),
+            (key(KeyCode::Right), Action::Right),
+            (key(KeyCode::Home), Action::LineStart),
+            (key(KeyCode::End), Action::LineEnd),
+            (key(KeyCode::PageUp), Action::PageUp),
+            (key(KeyCode::PageDown), Action::PageDown),
+            (key(KeyCode::Esc), Action::ClearSearch),
+            (KeyEvent::ctrl('e'), Action::ToggleLineEnding),
+            (KeyEvent::alt('n'), Action::CycleLineNumbers),
+            (KeyEvent::alt('w'), Action::ToggleWrap),
+        ];
+        Keymap {
+            bindings: bindings
+                .into_iter()
+                .map(|(key, action)| (vec![key], Command::from(action)))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Binds `keys` to `command`, or unbinds them if it is None. A key can't both run a
+    /// command and start a chord, so a binding that clashes with this one that way is removed.
+    pub fn bind(&mut self, keys: Vec<KeyEvent>, command: Option<Command>) {
+        self.bindings
+            .retain(|(bound, _)| !bound.starts_with(&keys) && !keys.starts_with(bound));
+        if let Some(command) = command {
+            self.bindings.push((keys, command));
+        }
+    }
+
+    /// What `keys` are bound to. A key that isn't bound with the modifiers held falls back to
+    /// the same key without shift, and then a key that isn't a character to the key without
+    /// any modifiers, so shift-up still moves up and ctrl-enter still starts a new line.
+    pub fn lookup(&self, keys: &[KeyEvent]) -> Binding {
+        let Some((&last, start)) = keys.split_last() else {
+            return Binding::Unbound;
+        };
+        let mut fallbacks = vec![last];
+        if last.modifiers.shift {
+            let modifiers = Modifiers {
+                shift: false,
+                ..last.modifiers
+            };
+            fallbacks.push(KeyEvent::new(last.code, modifiers));
+        }
+        if !matches!(last.code, KeyCode::Char(_)) {
+            fallbacks.push(KeyEvent::plain(last.code));
+        }
+
+        for last in fallbacks {
+            let keys = [start, &[last]].concat();
+            let binding = self.find(&keys);
+            if binding != Binding::Unbound {
+                return binding;
+            }
+        }
+        Binding::Unbound
+    }
+
+    fn find(&self, keys: &[KeyEvent]) -> Binding {
+        let mut binding = Binding::Unbound;
+        for (bound, command) in &self.bindings {
+            if bound == keys {
+                return Binding::Command(command.clone());
+            }
+            if bound.starts_with(keys) {
+                binding = Binding::Prefix;
+            }
+        }
+        binding
+    }
+
+    /// The first keys found bound to `action`.
+    pub fn keys_for(&self, action: Action) -> Option<&[KeyEvent]> {
+        self.bindings
+            .iter()
+            .find(|(_, command)| command.action == action)
+            .map(|(keys, _)| keys.as_slice())
+    }
+
+    /// The chords that start with `keys`, as the keys left to press and the command they run.
+    pub fn chords_after<'a>(
+        &'a self,
+        keys: &'a [KeyEvent],
+    ) -> impl Iterator<Item = (&'a [KeyEvent], &'a Command)> + 'a {
+        self.bindings
+            .iter()
+            .filter(move |(bound, _)| bound.len() > keys.len() && bound.starts_with(keys))
+            .map(move |(bound, command)| (&bound[keys.len()..], command))
+    }
+}
+
+/// Parses keys separated by spaces, such as `ctrl-x ctrl-s`.
+pub fn parse_keys(keys: &str) -> Option<Vec<KeyEvent>> {
+    let keys: Option<Vec<KeyEvent>> = keys.split_whitespace().map(KeyEvent::from_name).collect();
+    keys.filter(|keys| !keys.is_empty())
+}
+
+/// Writes keys the way `parse_keys` reads them.
+pub fn keys_name(keys: &[KeyEvent]) -> String {
+    let names: Vec<String> = keys.iter().map(KeyEvent::to_string).collect();
+    names.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(names: &str) -> Vec<KeyEvent> {
+        parse_keys(names).unwrap()
+    }
+
+    fn command(action: Action) -> Binding {
+        Binding::Command(Command::from(action))
+    }
+
+    #[test]
+    fn lookup() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.lookup(&keys("ctrl-s")), command(Action::Save));
+        assert_eq!(keymap.lookup(&keys("ctrl-k")), Binding::Unbound);
+        assert_eq!(keymap.lookup(&[]), Binding::Unbound);
+
+        // shift and then, for keys that aren't characters, all modifiers are let go of
+        let shift_up = KeyEvent::new(
+            KeyCode::Up,
+            Modifiers {
+                shift: true,
+                ..Modifiers::default()
+            },
+        );
+        assert_eq!(keymap.lookup(&[shift_up]), command(Action::Up));
+        let ctrl_enter = KeyEvent::new(
+            KeyCode::Enter,
+            Modifiers {
+                ctrl: true,
+                ..Modifiers::default()
+            },
+        );
+        assert_eq!(keymap.lookup(&[ctrl_enter]), command(Action::NewLine));
+        let shift_ctrl_s = KeyEvent::new(
+            KeyCode::Char('s'),
+            Modifiers {
+                shift: true,
+                ctrl: true,
+                alt: false,
+            },
+        );
+        assert_eq!(keymap.lookup(&[shift_ctrl_s]), command(Action::Save));
+        assert_eq!(keymap.lookup(&[KeyEvent::alt('s')]), Binding::Unbound);
+    }
+
+    #[test]
+    fn chords() {
+        let mut keymap = Keymap::default();
+        keymap.bind(keys("ctrl-x ctrl-s"), Some(Command::from(Action::Save)));
+        keymap.bind(keys("ctrl-x ctrl-c"), Some(Command::from(Action::Quit)));
+
+        assert_eq!(keymap.lookup(&keys("ctrl-x")), Binding::Prefix);
+        assert_eq!(keymap.lookup(&keys("ctrl-x ctrl-s")), command(Action::Save));
+        assert_eq!(keymap.lookup(&keys("ctrl-x ctrl-c")), command(Action::Quit));
+        assert_eq!(keymap.lookup(&keys("ctrl-x ctrl-q")), Binding::Unbound);
+        assert_eq!(
+            keymap.lookup(&keys("ctrl-x ctrl-s ctrl-s")),
+            Binding::Unbound
+        );
+
+        let after: Vec<String> = keymap
+            .chords_after(&keys("ctrl-x"))
+            .map(|(rest, command)| format!("{} {}", keys_name(rest), command.action.name()))
+            .collect();
+        assert_eq!(after, ["Ctrl-S save", "Ctrl-C quit"]);
+    }
+
+    #[test]
+    fn bind_removes_clashing_bindings() {
+        let mut keymap = Keymap::default();
+
+        // ctrl-x can't cut and start a chord at once
+        keymap.bind(keys("ctrl-x ctrl-s"), Some(Command::from(Action::Save)));
+        assert_eq!(keymap.keys_for(Action::Cut), None);
+        assert_eq!(keymap.keys_for(Action::Save), Some(&keys("ctrl-s")[..]));
+
+        // and binding it again takes the chord away
+        keymap.bind(keys("ctrl-x"), Some(Command::from(Action::Cut)));
+        assert_eq!(keymap.lookup(&keys("ctrl-x")), command(Action::Cut));
+        assert_eq!(keymap.lookup(&keys("ctrl-x ctrl-s")), Binding::Unbound);
+        assert_eq!(keymap.chords_after(&keys("ctrl-x")).count(), 0);
+
+        // a key bound twice runs the last command
+        keymap.bind(keys("ctrl-s"), Some(Command::from(Action::Quit)));
+        assert_eq!(keymap.lookup(&keys("ctrl-s")), command(Action::Quit));
+        assert_eq!(keymap.keys_for(Action::Save), None);
+
+        keymap.bind(keys("ctrl-s"), None);
+        assert_eq!(keymap.lookup(&keys("ctrl-s")), Binding::Unbound);
+    }
+
+    #[test]
+    fn parse_and_name_keys() {
+        let chord = keys("ctrl-x  ctrl-s");
+        assert_eq!(chord, [KeyEvent::ctrl('x'), KeyEvent::ctrl('s')]);
+        assert_eq!(keys_name(&chord), "Ctrl-X Ctrl-S");
+        assert_eq!(parse_keys(&keys_name(&chord)), Some(chord));
+        assert_eq!(parse_keys(""), None);
+        assert_eq!(parse_keys("ctrl-x bogus"), None);
+    }
+}
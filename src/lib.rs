@@ -0,0 +1,70 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The editing core of ronto. A `Buffer` holds the text of a file along with its undo history,
+//! and a `View` keeps track of the cursor and of what part of the buffer is on screen. Neither
+//! needs a terminal. The `Editor` draws them to a `Backend`, either the real `Terminal` or a
+//! `VirtualScreen` kept in memory, which lets whole editing sessions run without a terminal.
+
+mod backend;
+mod buffer;
+mod clipboard;
+mod command;
+mod config;
+mod editor;
+mod file;
+mod goto;
+mod history;
+mod key;
+mod keymap;
+mod mapped;
+mod row;
+mod screen;
+mod search;
+mod syntax;
+mod terminal;
+mod text;
+mod view;
+
+pub use backend::Backend;
+pub use buffer::Buffer;
+pub use clipboard::{copy_with_command, osc52_sequence, ClipboardMode};
+pub use command::{Action, Command};
+pub use config::{Colors, Config};
+pub use editor::{Editor, RONTO_VERSION};
+pub use file::{raw_byte, LineEnding};
+pub use goto::{GotoLine, GotoTarget};
+pub use history::EditKind;
+pub use key::{
+    Event, KeyCode, KeyDecoder, KeyEvent, Modifiers, MouseButton, MouseEvent, MouseKind,
+};
+pub use keymap::{keys_name, parse_keys, Binding, Keymap};
+pub use row::{
+    byte_to_cursorx, cursorx_to_byte, cursorx_to_renderx, grapheme_width, is_drawn_inverted,
+    line_len, renderx_to_cursorx, str_width, truncate_to_width, wrap_points, Row, DEFAULT_TAB_STOP,
+};
+pub use screen::{Cell, Style, VirtualScreen};
+pub use search::SearchPattern;
+pub use syntax::{
+    select_syntax, EditorSyntax, Highlight, HLDB, HL_HIGHLIGHT_NUMBERS, HL_HIGHLIGHT_STRINGS,
+};
+pub use terminal::Terminal;
+pub use view::{Direction, LineNumbers, View};
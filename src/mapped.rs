@@ -0,0 +1,723 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::file::{decode_line, encode_line, LineEnding};
+use memchr::{memchr, memchr_iter};
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::iter;
+use std::mem;
+use std::os::unix::fs::MetadataExt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/// Only every this many lines is the start of a line kept in the index, the ones in between
+/// are found by looking for '\n'. That keeps the index small for files with hundreds of
+/// millions of lines.
+const INDEX_STRIDE: usize = 64;
+
+/// How much of the file is looked through before the lines in it are made available.
+const INDEX_BLOCK_SIZE: usize = 4 << 20;
+
+/// Where the lines of a mapped file start, filled in by a background thread.
+#[derive(Debug)]
+struct LineIndex {
+    starts: RwLock<Vec<usize>>, // start of every INDEX_STRIDE-th line
+    lines: AtomicUsize,         // how many lines are indexed so far
+    done: AtomicBool,           // if the whole file is indexed
+    bare_lf: AtomicBool,        // if a line was found that ends in '\n' without '\r'
+    cancelled: AtomicBool,      // if the file was closed before that
+}
+
+impl LineIndex {
+    /// Waits until a line ending in a bare '\n' is found or the whole file is indexed, and
+    /// says if there is one.
+    fn wait_for_bare_lf(&self) -> bool {
+        loop {
+            let done = self.done.load(Ordering::Acquire);
+            if self.bare_lf.load(Ordering::Acquire) {
+                return true;
+            }
+            if done {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+/// A file mapped into memory. The lines become available as they are indexed, so the start
+/// of a huge file can be shown right away.
+#[derive(Debug)]
+pub(crate) struct MappedFile {
+    map: Arc<Mmap>,
+    index: Arc<LineIndex>,
+    first_crlf: bool,    // if the first line ends in CRLF
+    final_newline: bool, // if the last line is terminated too
+    id: (u64, u64),      // device and inode the file was mapped from
+}
+
+impl MappedFile {
+    /// Maps `filename` and starts indexing its lines, without waiting for any of them.
+    pub(crate) fn open(filename: &str) -> io::Result<MappedFile> {
+        MappedFile::open_indexed_after(filename, Duration::ZERO)
+    }
+
+    /// Like `open`, but only starts indexing after `delay`, so tests can see the file while
+    /// it's still loading.
+    pub(crate) fn open_indexed_after(filename: &str, delay: Duration) -> io::Result<MappedFile> {
+        let file = File::open(filename)?;
+        let metadata = file.metadata()?;
+        // SAFETY: the file is only read. If another program truncates it while it is open,
+        // reading the missing part kills the editor, as it does in any program mapping it.
+        let map = Arc::new(unsafe { Mmap::map(&file)? });
+
+        let first_line = match memchr(b'\n', &map) {
+            Some(i) => &map[..=i],
+            None => &[],
+        };
+        let index = Arc::new(LineIndex {
+            starts: RwLock::new(vec![0]),
+            lines: AtomicUsize::new(0),
+            done: AtomicBool::new(false),
+            bare_lf: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+        });
+        let (thread_map, thread_index) = (Arc::clone(&map), Arc::clone(&index));
+        thread::Builder::new()
+            .name("line index".to_string())
+            .spawn(move || {
+                thread::sleep(delay);
+                build_index(&thread_map, &thread_index);
+            })?;
+
+        Ok(MappedFile {
+            first_crlf: first_line.ends_with(b"\r\n"),
+            final_newline: map.last().map_or(true, |b| *b == b'\n'),
+            map,
+            index,
+            id: (metadata.dev(), metadata.ino()),
+        })
+    }
+
+    /// How lines are terminated as far as the file is indexed. As with a file that is read in,
+    /// that's only CRLF if every line has one, so it can still turn into LF while loading.
+    pub(crate) fn line_ending(&self) -> LineEnding {
+        if self.first_crlf && !self.index.bare_lf.load(Ordering::Acquire) {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// The line ending the whole file is decoded with. While the file is loading this waits
+    /// for the index to come across a line without CRLF, or to reach the end of the file.
+    pub(crate) fn wait_for_line_ending(&self) -> LineEnding {
+        if self.first_crlf && !self.index.wait_for_bare_lf() {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    pub(crate) fn final_newline(&self) -> bool {
+        self.final_newline
+    }
+
+    /// How many lines are indexed so far.
+    fn lines(&self) -> usize {
+        self.index.lines.load(Ordering::Acquire)
+    }
+
+    fn is_indexed(&self) -> bool {
+        self.index.done.load(Ordering::Acquire)
+    }
+
+    /// If `filename` is the file that was mapped, even if it was renamed since.
+    fn is_file(&self, filename: &str) -> bool {
+        fs::metadata(filename).is_ok_and(|m| (m.dev(), m.ino()) == self.id)
+    }
+
+    /// The byte offset where line `at` starts, the end of the file past the last line.
+    fn line_start(&self, at: usize) -> usize {
+        let starts = self.index.starts.read().unwrap();
+        let checkpoint = (at / INDEX_STRIDE).min(starts.len() - 1);
+        let mut start = starts[checkpoint];
+        drop(starts);
+
+        for _ in checkpoint * INDEX_STRIDE..at {
+            match memchr(b'\n', &self.map[start..]) {
+                Some(i) => start += i + 1,
+                None => return self.map.len(),
+            }
+        }
+        start
+    }
+
+    /// Line `at`, decoded the same way a file that is read in is.
+    fn line(&self, at: usize) -> String {
+        self.lines_from(at).next().unwrap_or_default()
+    }
+
+    /// The lines from line `at` to the end of the file, indexed yet or not.
+    fn lines_from(&self, at: usize) -> impl Iterator<Item = String> + '_ {
+        let mut rest = &self.map[self.line_start(at)..];
+        iter::from_fn(move || {
+            if rest.is_empty() {
+                return None;
+            }
+            let line = match memchr(b'\n', rest) {
+                Some(i) => {
+                    let line = &rest[..i];
+                    rest = &rest[i + 1..];
+                    match line.strip_suffix(b"\r") {
+                        Some(stripped) if self.line_ending() == LineEnding::CrLf => stripped,
+                        _ => line,
+                    }
+                }
+                None => mem::take(&mut rest),
+            };
+            Some(decode_line(line))
+        })
+    }
+
+    /// Splits the line ending off the last line in `bytes`, None if that line is the last
+    /// one of the file and isn't terminated.
+    fn split_terminator<'a>(&self, bytes: &'a [u8]) -> (&'a [u8], Option<&'a [u8]>) {
+        let lines = match bytes.strip_suffix(b"\n") {
+            Some(lines) => match lines.strip_suffix(b"\r") {
+                Some(stripped) if self.line_ending() == LineEnding::CrLf => stripped,
+                _ => lines,
+            },
+            None => return (bytes, None),
+        };
+        (lines, Some(&bytes[lines.len()..]))
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        self.index.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Finds where the lines of `map` start, making them available a block at a time.
+fn build_index(map: &[u8], index: &LineIndex) {
+    let mut lines = 0;
+    let mut starts = Vec::new();
+    let mut bare_lf = false;
+
+    for (block, bytes) in map.chunks(INDEX_BLOCK_SIZE).enumerate() {
+        if index.cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let offset = block * INDEX_BLOCK_SIZE;
+        for i in memchr_iter(b'\n', bytes) {
+            let end = offset + i;
+            if !bare_lf && (end == 0 || map[end - 1] != b'\r') {
+                bare_lf = true;
+                index.bare_lf.store(true, Ordering::Release);
+            }
+            lines += 1;
+            if lines % INDEX_STRIDE == 0 {
+                starts.push(offset + i + 1);
+            }
+        }
+        index.starts.write().unwrap().append(&mut starts);
+        index.lines.store(lines, Ordering::Release);
+    }
+
+    // an unterminated last line
+    if map.last().is_some_and(|b| *b != b'\n') {
+        index.lines.store(lines + 1, Ordering::Release);
+    }
+    index.done.store(true, Ordering::Release);
+}
+
+/// A run of lines in a `MappedText`.
+#[derive(Debug, Clone, Copy)]
+enum Piece {
+    Mapped { start: usize, end: Option<usize> }, // lines of the file, to its end if None
+    Added { start: usize, end: usize },          // lines in `added`
+}
+
+/// The lines of a mapped file with edits layered on top of it, as a list of pieces that are
+/// either lines of the file or lines added since. Nothing of the file is copied, and saving
+/// writes the parts that didn't change out as they are. The last piece always reaches to the
+/// end of the file, which moves down while the file is being indexed.
+#[derive(Debug)]
+pub(crate) struct MappedText {
+    file: MappedFile,
+    added: Vec<String>, // every line inserted or changed, never removed from
+    pieces: Vec<Piece>,
+    firsts: Vec<usize>, // the line each piece starts at
+}
+
+impl MappedText {
+    pub(crate) fn new(file: MappedFile) -> MappedText {
+        MappedText {
+            file,
+            added: Vec::new(),
+            pieces: vec![Piece::Mapped {
+                start: 0,
+                end: None,
+            }],
+            firsts: vec![0],
+        }
+    }
+
+    pub(crate) fn is_loading(&self) -> bool {
+        !self.file.is_indexed()
+    }
+
+    pub(crate) fn is_mapped_from(&self, filename: &str) -> bool {
+        self.file.is_file(filename)
+    }
+
+    pub(crate) fn line_ending(&self) -> LineEnding {
+        self.file.line_ending()
+    }
+
+    pub(crate) fn wait_for_line_ending(&self) -> LineEnding {
+        self.file.wait_for_line_ending()
+    }
+
+    fn piece_len(&self, piece: &Piece) -> usize {
+        match *piece {
+            Piece::Mapped { start, end } => end.unwrap_or(self.file.lines().max(start)) - start,
+            Piece::Added { start, end } => end - start,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        let last = self.pieces.len() - 1;
+        self.firsts[last] + self.piece_len(&self.pieces[last])
+    }
+
+    /// Waits until line `at` is indexed, or the whole file is and it turns out not to exist.
+    pub(crate) fn wait_for_line(&self, at: usize) {
+        while self.len() <= at && self.is_loading() {
+            // indexing a block only takes a few milliseconds
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// The piece line `at` is in and how far into it the line is. Past the last line, that's
+    /// the end of the last piece.
+    fn locate(&self, at: usize) -> (usize, usize) {
+        let last = self.pieces.len() - 1;
+        if at >= self.len() {
+            return (last, self.piece_len(&self.pieces[last]));
+        }
+        // the last of the pieces starting at or before `at`, as empty ones are skipped over
+        let i = self.firsts.partition_point(|first| *first <= at) - 1;
+        (i, at - self.firsts[i])
+    }
+
+    /// Moves the pieces from `from` on down by `lines`, up if negative.
+    fn shift(&mut self, from: usize, lines: isize) {
+        for first in &mut self.firsts[from..] {
+            *first = first.saturating_add_signed(lines);
+        }
+    }
+
+    /// Joins the piece at `i` with the one before it if they are lines that follow each
+    /// other, so that the pieces don't keep adding up as lines are deleted and put back.
+    fn merge(&mut self, i: usize) {
+        if i == 0 || i >= self.pieces.len() {
+            return;
+        }
+        let merged = match (self.pieces[i - 1], self.pieces[i]) {
+            (
+                Piece::Mapped {
+                    start,
+                    end: Some(mid),
+                },
+                Piece::Mapped { start: next, end },
+            ) if mid == next => Piece::Mapped { start, end },
+            (Piece::Added { start, end: mid }, Piece::Added { start: next, end })
+                if mid == next =>
+            {
+                Piece::Added { start, end }
+            }
+            _ => return,
+        };
+        self.pieces[i - 1] = merged;
+        self.pieces.remove(i);
+        self.firsts.remove(i);
+    }
+
+    /// Splits the pieces so that one starts at line `at` and returns its position.
+    fn split(&mut self, at: usize) -> usize {
+        let (i, offset) = self.locate(at);
+        if offset == 0 {
+            return i;
+        }
+
+        let (left, right) = match self.pieces[i] {
+            Piece::Mapped { start, end } => (
+                Piece::Mapped {
+                    start,
+                    end: Some(start + offset),
+                },
+                Piece::Mapped {
+                    start: start + offset,
+                    end,
+                },
+            ),
+            Piece::Added { start, end } => (
+                Piece::Added {
+                    start,
+                    end: start + offset,
+                },
+                Piece::Added {
+                    start: start + offset,
+                    end,
+                },
+            ),
+        };
+        self.pieces[i] = left;
+        self.pieces.insert(i + 1, right);
+        self.firsts.insert(i + 1, at);
+        i + 1
+    }
+
+    pub(crate) fn line(&self, at: usize) -> Cow<'_, str> {
+        let (i, offset) = self.locate(at);
+        match self.pieces[i] {
+            Piece::Mapped { start, .. } => Cow::Owned(self.file.line(start + offset)),
+            Piece::Added { start, .. } => Cow::Borrowed(&self.added[start + offset]),
+        }
+    }
+
+    pub(crate) fn lines_from(&self, at: usize) -> impl Iterator<Item = Cow<'_, str>> {
+        let (first, offset) = self.locate(at);
+        self.pieces[first..]
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, piece)| {
+                let skip = if i == 0 { offset } else { 0 };
+                self.piece_lines(piece, skip)
+            })
+    }
+
+    /// The lines of `piece` after the first `skip`, only as far as the file is indexed.
+    fn piece_lines(
+        &self,
+        piece: &Piece,
+        skip: usize,
+    ) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        match *piece {
+            Piece::Mapped { start, .. } => Box::new(
+                self.file
+                    .lines_from(start + skip)
+                    .take(self.piece_len(piece) - skip)
+                    .map(Cow::Owned),
+            ),
+            Piece::Added { start, end } => Box::new(
+                self.added[start + skip..end]
+                    .iter()
+                    .map(|line| Cow::Borrowed(line.as_str())),
+            ),
+        }
+    }
+
+    pub(crate) fn insert_lines(&mut self, at: usize, lines: &[String]) {
+        if lines.is_empty() {
+            return;
+        }
+        let i = self.split(at);
+        let (start, end) = (self.added.len(), self.added.len() + lines.len());
+        self.added.extend_from_slice(lines);
+
+        self.pieces.insert(i, Piece::Added { start, end });
+        self.firsts.insert(i, at);
+        self.shift(i + 1, lines.len() as isize);
+        // lines inserted one after the other, like when a paste is redone, stay in one piece
+        self.merge(i);
+    }
+
+    pub(crate) fn remove_lines(&mut self, at: usize, count: usize) {
+        let start = self.split(at);
+        let end = self.split(at + count);
+        if end < self.pieces.len() {
+            let removed = self.firsts[end] - self.firsts[start];
+            self.shift(end, -(removed as isize));
+        }
+        self.pieces.drain(start..end);
+        self.firsts.drain(start..end);
+        self.merge(start);
+    }
+
+    pub(crate) fn set_line(&mut self, at: usize, line: &str) {
+        self.remove_lines(at, 1);
+        self.insert_lines(at, &[line.to_string()]);
+    }
+
+    /// Writes the lines to `out` the way they are saved, all the way to the end of the file
+    /// even if it isn't indexed yet. Lines of the file that are saved with the line ending
+    /// they were read with are copied over byte for byte.
+    pub(crate) fn write_to(
+        &self,
+        out: &mut dyn Write,
+        line_ending: LineEnding,
+        final_newline: bool,
+    ) -> io::Result<()> {
+        // the lines of the file are only decoded the same way all through it once this is known
+        let file_line_ending = self.file.wait_for_line_ending();
+        // a line is only terminated once it's known not to be the last one
+        let mut ending: Option<&[u8]> = None;
+        let mut buf = Vec::new();
+
+        for piece in &self.pieces {
+            match *piece {
+                Piece::Mapped { start, end } if line_ending == file_line_ending => {
+                    let from = self.file.line_start(start);
+                    let to = end.map_or(self.file.map.len(), |end| self.file.line_start(end));
+                    if from == to {
+                        continue;
+                    }
+                    let (lines, terminator) = self.file.split_terminator(&self.file.map[from..to]);
+                    out.write_all(ending.unwrap_or_default())?;
+                    out.write_all(lines)?;
+                    ending = Some(terminator.unwrap_or(line_ending.as_bytes()));
+                }
+                Piece::Mapped { start, end } => {
+                    let count = end.map_or(usize::MAX, |end| end - start);
+                    for line in self.file.lines_from(start).take(count) {
+                        buf.clear();
+                        encode_line(&line, &mut buf);
+                        out.write_all(ending.unwrap_or_default())?;
+                        out.write_all(&buf)?;
+                        ending = Some(line_ending.as_bytes());
+                    }
+                }
+                Piece::Added { start, end } => {
+                    for line in &self.added[start..end] {
+                        buf.clear();
+                        encode_line(line, &mut buf);
+                        out.write_all(ending.unwrap_or_default())?;
+                        out.write_all(&buf)?;
+                        ending = Some(line_ending.as_bytes());
+                    }
+                }
+            }
+        }
+
+        if final_newline {
+            out.write_all(ending.unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ronto-mapped-{}-{name}", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Maps `bytes` and waits for all of them to be indexed.
+    fn open(name: &str, bytes: &[u8]) -> MappedText {
+        let path = temp_file(name, bytes);
+        let text = MappedText::new(MappedFile::open(path.to_str().unwrap()).unwrap());
+        // the mapping stays valid once the file is gone
+        fs::remove_file(&path).unwrap();
+        text.wait_for_line(usize::MAX);
+        text
+    }
+
+    fn lines(text: &MappedText) -> Vec<String> {
+        text.lines_from(0).map(Cow::into_owned).collect()
+    }
+
+    fn saved(text: &MappedText, line_ending: LineEnding, final_newline: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        text.write_to(&mut out, line_ending, final_newline).unwrap();
+        out
+    }
+
+    /// Checks `text` against the lines it should have, reading them every way there is.
+    fn assert_lines(text: &MappedText, model: &[String]) {
+        assert_eq!(text.len(), model.len());
+        assert_eq!(lines(text), model);
+        for (at, line) in model.iter().enumerate() {
+            assert_eq!(text.line(at), *line, "line {at}");
+        }
+        for at in [
+            0,
+            1,
+            63,
+            64,
+            65,
+            model.len() / 2,
+            model.len().saturating_sub(1),
+        ] {
+            let from: Vec<String> = text.lines_from(at).map(Cow::into_owned).collect();
+            assert_eq!(from, model[at.min(model.len())..], "lines from {at}");
+        }
+
+        let expected: Vec<u8> = model
+            .iter()
+            .flat_map(|line| [line.as_bytes(), b"\n"])
+            .flatten()
+            .copied()
+            .collect();
+        assert_eq!(saved(text, LineEnding::Lf, true), expected);
+    }
+
+    #[test]
+    fn unedited_files_save_byte_for_byte() {
+        let files: &[&[u8]] = &[
+            b"one\ntwo\n",
+            b"one\ntwo",
+            b"one\r\ntwo\r\n",
+            b"one\r\ntwo\r\nthree",
+            b"one\r\ntwo\nthree\r\n",
+            b"caf\xe9\n\xff\r\n",
+            b"\n\n",
+            b"",
+        ];
+        for (i, bytes) in files.iter().enumerate() {
+            let text = open(&format!("unedited-{i}"), bytes);
+            let file = &text.file;
+            assert_eq!(
+                saved(&text, file.line_ending(), file.final_newline()),
+                *bytes
+            );
+        }
+    }
+
+    #[test]
+    fn line_endings_follow_the_decode_rule() {
+        let bytes = b"one\r\ntwo\nthree\r\n";
+        let text = open("mixed", bytes);
+        let decoded = crate::file::decode(bytes);
+        assert_eq!(text.file.line_ending(), decoded.line_ending);
+        assert_eq!(lines(&text), decoded.lines);
+
+        // a bare '\n' far into the file still counts
+        let mut bytes = b"crlf\r\n".repeat(INDEX_BLOCK_SIZE / 3);
+        bytes.extend_from_slice(b"lf\n");
+        let text = open("mixed-late", &bytes);
+        assert_eq!(text.file.line_ending(), LineEnding::Lf);
+        assert_eq!(text.line(0), "crlf\r");
+
+        // opening doesn't wait to find that out, the lines found so far count until then
+        let path = temp_file("mixed-loading", &bytes);
+        let delay = Duration::from_millis(50);
+        let file = MappedFile::open_indexed_after(path.to_str().unwrap(), delay).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!file.is_indexed());
+        assert_eq!(file.line_ending(), LineEnding::CrLf);
+        assert_eq!(file.wait_for_line_ending(), LineEnding::Lf);
+        assert_eq!(file.line_ending(), LineEnding::Lf);
+        assert_eq!(file.line(0), "crlf\r");
+
+        let text = open("crlf", b"one\r\ntwo\r\n");
+        assert_eq!(text.file.line_ending(), LineEnding::CrLf);
+        assert_eq!(lines(&text), ["one", "two"]);
+        assert_eq!(saved(&text, LineEnding::Lf, true), b"one\ntwo\n");
+    }
+
+    #[test]
+    fn edits_across_pieces() {
+        let mut model: Vec<String> = (0..200).map(|i| format!("line {i}")).collect();
+        let mut file = model.join("\n");
+        file.push('\n');
+        let mut text = open("edits", file.as_bytes());
+        assert_lines(&text, &model);
+
+        let added = |from: usize, count: usize| -> Vec<String> {
+            (from..from + count).map(|i| format!("added {i}")).collect()
+        };
+
+        // insert inside the file, at its start and at its end
+        text.insert_lines(10, &added(0, 3));
+        model.splice(10..10, added(0, 3));
+        assert_lines(&text, &model);
+        text.insert_lines(0, &added(3, 1));
+        model.splice(0..0, added(3, 1));
+        assert_lines(&text, &model);
+        let len = model.len();
+        text.insert_lines(len, &added(4, 2));
+        model.splice(len..len, added(4, 2));
+        assert_lines(&text, &model);
+
+        // a delete that starts in the file and ends in added lines, and one over many pieces
+        text.remove_lines(8, 4);
+        model.drain(8..12);
+        assert_lines(&text, &model);
+        text.set_line(70, "changed");
+        model[70] = "changed".to_string();
+        text.set_line(71, "changed too");
+        model[71] = "changed too".to_string();
+        assert_lines(&text, &model);
+        text.remove_lines(5, 100);
+        model.drain(5..105);
+        assert_lines(&text, &model);
+
+        // lines inserted one after the other stay in one piece
+        let pieces = text.pieces.len();
+        text.insert_lines(20, &added(6, 1));
+        text.insert_lines(21, &added(7, 1));
+        model.splice(20..20, added(6, 2));
+        assert_lines(&text, &model);
+        assert_eq!(text.pieces.len(), pieces + 2);
+
+        // deleting everything leaves an empty text
+        let len = model.len();
+        text.remove_lines(0, len);
+        model.clear();
+        assert_lines(&text, &model);
+        text.insert_lines(0, &added(8, 1));
+        model.extend(added(8, 1));
+        assert_lines(&text, &model);
+    }
+
+    #[test]
+    fn lines_are_waited_for_while_loading() {
+        let bytes = "line\n".repeat(1000);
+        let path = temp_file("loading", bytes.as_bytes());
+        let file =
+            MappedFile::open_indexed_after(path.to_str().unwrap(), Duration::from_millis(50))
+                .unwrap();
+        fs::remove_file(&path).unwrap();
+        let text = MappedText::new(file);
+        assert!(text.is_loading());
+        assert_eq!(text.len(), 0);
+
+        text.wait_for_line(500);
+        assert!(text.len() > 500);
+        text.wait_for_line(usize::MAX);
+        assert!(!text.is_loading());
+        assert_eq!(text.len(), 1000);
+    }
+}
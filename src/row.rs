@@ -0,0 +1,248 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::file::raw_byte;
+use crate::syntax::Highlight;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub const DEFAULT_TAB_STOP: usize = 8;
+
+/// A line of text along with how it is drawn.
+#[derive(Debug)]
+pub struct Row {
+    pub(crate) line: String,
+    pub(crate) render: String,
+    pub(crate) hl: Vec<Highlight>, // highlight class of each byte in render
+}
+
+impl Row {
+    /// A row without highlighting, the buffer fills that in once it knows the rows around it.
+    /// Tabs are drawn up to the next multiple of `tab_stop`.
+    pub(crate) fn new(line: String, tab_stop: usize) -> Row {
+        let mut row = Row {
+            line,
+            render: String::new(),
+            hl: Vec::new(),
+        };
+        row.update_render(tab_stop);
+        row
+    }
+
+    /// The text of the row, without a line ending.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// The text as it is drawn, with tabs expanded to spaces.
+    pub fn render(&self) -> &str {
+        &self.render
+    }
+
+    /// The highlight class of each byte in `render`.
+    pub fn hl(&self) -> &[Highlight] {
+        &self.hl
+    }
+
+    pub(crate) fn update_render(&mut self, tab_stop: usize) {
+        let mut render = String::with_capacity(self.line.len());
+
+        let mut rx: usize = 0;
+        for g in self.line.graphemes(true) {
+            if g == "\t" {
+                render.push(' ');
+                rx += 1;
+                while rx % tab_stop != 0 {
+                    render.push(' ');
+                    rx += 1;
+                }
+            } else {
+                render.push_str(g);
+                rx += grapheme_width(g);
+            }
+        }
+
+        self.render = render;
+    }
+}
+
+/// How many screen cells a grapheme cluster takes up. Control characters and undecodable
+/// bytes are drawn as a single inverted symbol, and wide CJK and emoji characters take up
+/// two cells.
+pub fn grapheme_width(g: &str) -> usize {
+    if is_drawn_inverted(g) {
+        1
+    } else {
+        g.width().max(1)
+    }
+}
+
+pub fn is_drawn_inverted(g: &str) -> bool {
+    g.starts_with(|c: char| c.is_control() || raw_byte(c).is_some())
+}
+
+pub fn str_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// The longest prefix of `s` that fits in `width` screen cells.
+pub fn truncate_to_width(s: &str, width: usize) -> &str {
+    let mut used: usize = 0;
+    for (i, g) in s.grapheme_indices(true) {
+        used += grapheme_width(g);
+        if used > width {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+/// The render columns at which `render` is broken into lines at most `width` columns wide,
+/// starting with 0. Lines are broken after whitespace where possible, and a word too long for
+/// a line of its own is broken wherever it reaches the edge. A line that fills the width
+/// exactly is followed by an empty one, so a cursor at its end has somewhere to go.
+pub fn wrap_points(render: &str, width: usize) -> Vec<usize> {
+    let mut points = vec![0];
+    let mut line_start: usize = 0;
+    let mut after_space: Option<usize> = None;
+    let mut rx: usize = 0;
+
+    for g in render.graphemes(true) {
+        let g_width = grapheme_width(g);
+        let is_space = g.chars().all(char::is_whitespace);
+        while rx + g_width > line_start + width && rx > line_start {
+            line_start = match after_space {
+                Some(point) if point > line_start => point,
+                _ => rx,
+            };
+            points.push(line_start);
+            after_space = None;
+        }
+
+        rx += g_width;
+        if is_space {
+            after_space = Some(rx);
+        }
+    }
+
+    if rx > line_start && rx - line_start == width {
+        points.push(rx);
+    }
+    points
+}
+
+/// Number of grapheme clusters in `line`, which is also the cursor x at its end.
+pub fn line_len(line: &str) -> usize {
+    line.graphemes(true).count()
+}
+
+/// Byte offset of the grapheme cluster at index `cx`, or the length of the line past its end.
+pub fn cursorx_to_byte(line: &str, cx: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(cx)
+        .map_or(line.len(), |(index, _)| index)
+}
+
+/// Index of the grapheme cluster that starts at or contains the byte at `index`.
+pub fn byte_to_cursorx(line: &str, index: usize) -> usize {
+    line.grapheme_indices(true)
+        .take_while(|(i, g)| i + g.len() <= index)
+        .count()
+}
+
+pub fn cursorx_to_renderx(line: &str, cx: usize, tab_stop: usize) -> usize {
+    let mut rx: usize = 0;
+
+    for g in line.graphemes(true).take(cx) {
+        if g == "\t" {
+            rx += (tab_stop - 1) - (rx % tab_stop);
+            rx += 1;
+        } else {
+            rx += grapheme_width(g);
+        }
+    }
+
+    rx
+}
+
+/// The inverse of `cursorx_to_renderx`: the grapheme cluster drawn at render column `rx`, or
+/// the end of the line if `rx` is past it.
+pub fn renderx_to_cursorx(line: &str, rx: usize, tab_stop: usize) -> usize {
+    let mut cur_rx: usize = 0;
+
+    for (cx, g) in line.graphemes(true).enumerate() {
+        if g == "\t" {
+            cur_rx += (tab_stop - 1) - (cur_rx % tab_stop);
+            cur_rx += 1;
+        } else {
+            cur_rx += grapheme_width(g);
+        }
+
+        if cur_rx > rx {
+            return cx;
+        }
+    }
+
+    line_len(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_points_break_after_spaces() {
+        assert_eq!(wrap_points("", 10), [0]);
+        assert_eq!(wrap_points("hello", 10), [0]);
+        assert_eq!(wrap_points("hello world", 8), [0, 6]);
+        assert_eq!(wrap_points("one two three", 8), [0, 8]);
+        assert_eq!(wrap_points("abc defg", 4), [0, 4, 8]);
+    }
+
+    #[test]
+    fn wrap_points_break_words_too_long_for_a_line() {
+        assert_eq!(wrap_points("abcdefghij", 4), [0, 4, 8]);
+        assert_eq!(wrap_points("ab cdefghij", 4), [0, 3, 7, 11]);
+    }
+
+    #[test]
+    fn wrap_points_leave_room_for_a_cursor_after_a_full_line() {
+        assert_eq!(wrap_points("abcd", 4), [0, 4]);
+        assert_eq!(wrap_points("abcdefgh", 4), [0, 4, 8]);
+        assert_eq!(wrap_points("abc", 4), [0]);
+    }
+
+    #[test]
+    fn wrap_points_count_screen_cells() {
+        // a double-width character that would straddle the edge goes to the next line
+        assert_eq!(wrap_points("abc漢", 4), [0, 3]);
+        assert_eq!(wrap_points("ab漢", 4), [0, 4]);
+        assert_eq!(wrap_points("漢字漢字漢", 4), [0, 4, 8]);
+        // even when it is wider than the whole line
+        assert_eq!(wrap_points("漢a", 1), [0, 2, 3]);
+
+        // tabs are wrapped as the spaces they are drawn with
+        let row = Row::new("a\tbcdef".to_string(), DEFAULT_TAB_STOP);
+        assert_eq!(wrap_points(row.render(), 10), [0, 8]);
+        let row = Row::new("\t\t".to_string(), 4);
+        assert_eq!(wrap_points(row.render(), 3), [0, 3, 6]);
+    }
+}
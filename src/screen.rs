@@ -0,0 +1,444 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::backend::Backend;
+use crate::row::grapheme_width;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::mem;
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Colors and attributes set by SGR escape codes. Colors are the 0-15 palette indexes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<u8>,
+    pub bg: Option<u8>,
+    pub reverse: bool,
+}
+
+/// One cell of the screen grid. A double width grapheme is stored in its first cell, the cell
+/// after it has an empty symbol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: String,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell {
+            symbol: String::from(" "),
+            style: Style::default(),
+        }
+    }
+}
+
+/// A backend that keeps the screen in memory. Whatever the editor writes is parsed into a
+/// grid of cells, and its input comes from a queue filled with `push_input`.
+#[derive(Debug)]
+pub struct VirtualScreen {
+    cells: Vec<Vec<Cell>>,
+    cols: usize,
+    cursor: (usize, usize), // (row, column)
+    cursor_visible: bool,
+    style: Style,
+    last_cell: Option<(usize, usize)>, // where the last grapheme went, to extend it
+    pending: Vec<u8>,                  // an escape code or character split across writes
+    input: VecDeque<Vec<u8>>,
+    clipboard: Option<String>, // the last text put on the clipboard with OSC 52
+    input_split: bool, // the first queued input was partly read, the rest follows right away
+    paused: bool,      // a whole push was read, the next one comes after a pause
+    resized: bool,
+}
+
+impl VirtualScreen {
+    pub fn new(rows: usize, cols: usize) -> VirtualScreen {
+        VirtualScreen {
+            cells: vec![vec![Cell::default(); cols]; rows],
+            cols,
+            cursor: (0, 0),
+            cursor_visible: true,
+            style: Style::default(),
+            last_cell: None,
+            pending: Vec::new(),
+            input: VecDeque::new(),
+            clipboard: None,
+            input_split: false,
+            paused: false,
+            resized: false,
+        }
+    }
+
+    /// Queues input for the editor. Every call arrives as if typed after a pause, so an ESC
+    /// pushed on its own is the Esc key and not the start of a sequence. Once the queue is
+    /// empty reads return 0 and the editor quits.
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        if !bytes.is_empty() {
+            self.input.push_back(bytes.to_vec());
+        }
+    }
+
+    /// Changes the size of the screen, the editor sees it on its next read.
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        self.cells.resize(rows, vec![Cell::default(); cols]);
+        for row in self.cells.iter_mut() {
+            row.resize(cols, Cell::default());
+        }
+        self.cols = cols;
+        self.cursor.0 = self.cursor.0.min(rows.saturating_sub(1));
+        self.cursor.1 = self.cursor.1.min(cols);
+        self.last_cell = None;
+        self.resized = true;
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> &Cell {
+        &self.cells[row][col]
+    }
+
+    /// The symbols of a row, including trailing blanks.
+    pub fn row_text(&self, row: usize) -> String {
+        self.cells[row]
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect()
+    }
+
+    /// All rows, separated by newlines.
+    pub fn text(&self) -> String {
+        (0..self.cells.len())
+            .map(|row| self.row_text(row))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn clipboard(&self) -> Option<&str> {
+        self.clipboard.as_deref()
+    }
+
+    fn rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn blank(&self) -> Cell {
+        // erasing keeps the background color but no other attributes
+        Cell {
+            symbol: String::from(" "),
+            style: Style {
+                bg: self.style.bg,
+                ..Style::default()
+            },
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor.0 + 1 < self.rows() {
+            self.cursor.0 += 1;
+        } else if self.rows() > 0 {
+            self.cells.remove(0);
+            self.cells.push(vec![Cell::default(); self.cols]);
+        }
+    }
+
+    /// Handles everything in `pending` that is complete, leaving a partial sequence behind.
+    fn parse(&mut self) {
+        let bytes = std::mem::take(&mut self.pending);
+        let mut i = 0;
+        while i < bytes.len() {
+            let used = match bytes[i] {
+                0x1b => self.parse_escape(&bytes[i..]),
+                b'\r' => {
+                    self.cursor.1 = 0;
+                    self.last_cell = None;
+                    Some(1)
+                }
+                b'\n' => {
+                    self.line_feed();
+                    self.last_cell = None;
+                    Some(1)
+                }
+                0x08 => {
+                    self.cursor.1 = self.cursor.1.saturating_sub(1);
+                    self.last_cell = None;
+                    Some(1)
+                }
+                b if b < 0x20 || b == 0x7f => Some(1),
+                _ => self.parse_char(&bytes[i..]),
+            };
+            match used {
+                Some(used) => i += used,
+                None => break,
+            }
+        }
+        self.pending = bytes[i..].to_vec();
+    }
+
+    /// Returns how many bytes the escape code took, None if it is not complete yet.
+    fn parse_escape(&mut self, bytes: &[u8]) -> Option<usize> {
+        self.last_cell = None;
+        match bytes.get(1)? {
+            b'[' => {}
+            b']' => return self.parse_osc(bytes),
+            _ => return Some(2),
+        }
+
+        let end = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b))? + 2;
+        let params = String::from_utf8_lossy(&bytes[2..end]);
+        let (private, params) = match params.strip_prefix('?') {
+            Some(params) => (true, params),
+            None => (false, &params[..]),
+        };
+        let args: Vec<usize> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+        let arg = |n: usize, default: usize| match args.get(n) {
+            Some(0) | None => default,
+            Some(&a) => a,
+        };
+
+        let (rows, cols) = (self.rows(), self.cols);
+        match (private, bytes[end]) {
+            (true, b'h') | (true, b'l') if args.contains(&25) => {
+                self.cursor_visible = bytes[end] == b'h';
+            }
+            (false, b'H') | (false, b'f') => {
+                self.cursor.0 = (arg(0, 1) - 1).min(rows.saturating_sub(1));
+                self.cursor.1 = (arg(1, 1) - 1).min(cols.saturating_sub(1));
+            }
+            (false, b'A') => self.cursor.0 = self.cursor.0.saturating_sub(arg(0, 1)),
+            (false, b'B') => {
+                self.cursor.0 = (self.cursor.0 + arg(0, 1)).min(rows.saturating_sub(1))
+            }
+            (false, b'C') => {
+                self.cursor.1 = (self.cursor.1 + arg(0, 1)).min(cols.saturating_sub(1))
+            }
+            (false, b'D') => self.cursor.1 = self.cursor.1.saturating_sub(arg(0, 1)),
+            (false, b'J') => {
+                let blank = self.blank();
+                let (row, col) = self.cursor;
+                for (r, line) in self.cells.iter_mut().enumerate() {
+                    for (c, cell) in line.iter_mut().enumerate() {
+                        let erase = match args[0] {
+                            0 => (r, c) >= (row, col),
+                            1 => (r, c) <= (row, col),
+                            _ => true,
+                        };
+                        if erase {
+                            *cell = blank.clone();
+                        }
+                    }
+                }
+            }
+            (false, b'K') => {
+                let blank = self.blank();
+                let (row, col) = self.cursor;
+                if let Some(line) = self.cells.get_mut(row) {
+                    let range = match args[0] {
+                        0 => col.min(cols)..cols,
+                        1 => 0..(col + 1).min(cols),
+                        _ => 0..cols,
+                    };
+                    for cell in &mut line[range] {
+                        *cell = blank.clone();
+                    }
+                }
+            }
+            (false, b'm') => self.set_style(&args),
+            (false, b'n') if args[0] == 6 => {
+                // cursor position report, answered as input like a terminal would
+                let reply = format!("\x1b[{};{}R", self.cursor.0 + 1, self.cursor.1 + 1);
+                self.push_input(reply.as_bytes());
+            }
+            _ => {}
+        }
+
+        Some(end + 1)
+    }
+
+    /// Handles an OSC sequence, ended by BEL or `ESC \\`. Only setting the clipboard is kept.
+    fn parse_osc(&mut self, bytes: &[u8]) -> Option<usize> {
+        let (body_end, len) = (2..bytes.len()).find_map(|i| match bytes[i] {
+            0x07 => Some((i, i + 1)),
+            0x1b if bytes.get(i + 1) == Some(&b'\\') => Some((i, i + 2)),
+            _ => None,
+        })?;
+
+        if let Some(data) = bytes[2..body_end].strip_prefix(b"52;c;") {
+            self.clipboard = STANDARD
+                .decode(data)
+                .ok()
+                .map(|text| String::from_utf8_lossy(&text).into_owned());
+        }
+        Some(len)
+    }
+
+    fn set_style(&mut self, args: &[usize]) {
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            match arg {
+                0 => self.style = Style::default(),
+                7 => self.style.reverse = true,
+                27 => self.style.reverse = false,
+                30..=37 => self.style.fg = Some((arg - 30) as u8),
+                39 => self.style.fg = None,
+                40..=47 => self.style.bg = Some((arg - 40) as u8),
+                49 => self.style.bg = None,
+                90..=97 => self.style.fg = Some((arg - 90 + 8) as u8),
+                100..=107 => self.style.bg = Some((arg - 100 + 8) as u8),
+                38 | 48 => {
+                    // 256 and true color, only the palette part is kept
+                    let color = match args.next() {
+                        Some(5) => args.next().map(|&c| c as u8),
+                        Some(2) => {
+                            args.nth(2);
+                            None
+                        }
+                        _ => None,
+                    };
+                    if arg == 38 {
+                        self.style.fg = color;
+                    } else {
+                        self.style.bg = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns how many bytes the character took, None if it is not complete yet.
+    fn parse_char(&mut self, bytes: &[u8]) -> Option<usize> {
+        let len = match bytes[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        let complete = bytes.len() >= len;
+        if !complete && bytes[1..].iter().all(|b| b & 0xc0 == 0x80) {
+            return None;
+        }
+        let (c, used) = match std::str::from_utf8(&bytes[..len.min(bytes.len())]) {
+            Ok(s) if complete => (s, len),
+            _ => ("\u{fffd}", 1),
+        };
+        self.print(c);
+        Some(used)
+    }
+
+    fn print(&mut self, c: &str) {
+        // a character that continues the last grapheme, like a combining mark, joins its cell
+        if let Some((row, col)) = self.last_cell {
+            let joined = format!("{}{}", self.cells[row][col].symbol, c);
+            if joined.graphemes(true).count() == 1 {
+                let old_width = grapheme_width(&self.cells[row][col].symbol);
+                let width = grapheme_width(&joined);
+                self.cells[row][col].symbol = joined;
+                if width > old_width && col + 1 < self.cols {
+                    self.cells[row][col + 1] = Cell {
+                        symbol: String::new(),
+                        style: self.style,
+                    };
+                    self.cursor.1 = col + width;
+                }
+                return;
+            }
+        }
+
+        let width = grapheme_width(c);
+        if self.cursor.1 + width > self.cols {
+            self.cursor.1 = 0;
+            self.line_feed();
+        }
+        if self.rows() == 0 || width > self.cols {
+            return;
+        }
+
+        let (row, col) = self.cursor;
+        self.cells[row][col] = Cell {
+            symbol: c.to_string(),
+            style: self.style,
+        };
+        if width == 2 {
+            self.cells[row][col + 1] = Cell {
+                symbol: String::new(),
+                style: self.style,
+            };
+        }
+        self.last_cell = Some((row, col));
+        self.cursor.1 += width;
+    }
+}
+
+impl Write for VirtualScreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        self.parse();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Backend for VirtualScreen {
+    fn read_input(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.resized {
+            self.resized = false;
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+
+        let Some(mut chunk) = self.input.pop_front() else {
+            return Ok(0);
+        };
+        let len = chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        self.input_split = len < chunk.len();
+        self.paused = !self.input_split;
+        if self.input_split {
+            self.input.push_front(chunk.split_off(len));
+        }
+        Ok(len)
+    }
+
+    fn poll_input(&mut self, _timeout: Duration) -> io::Result<bool> {
+        if self.resized || self.input_split {
+            return Ok(true);
+        }
+        // the first wait after a push is the pause before the next one, which is long enough
+        // to time out an escape sequence. After that the next push, or the end of the input,
+        // is there to read without waiting.
+        Ok(!mem::take(&mut self.paused))
+    }
+
+    fn size(&mut self) -> io::Result<(usize, usize)> {
+        Ok((self.rows(), self.cols))
+    }
+}
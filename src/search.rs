@@ -0,0 +1,88 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use regex::Regex;
+
+#[derive(Debug)]
+pub enum SearchPattern {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl SearchPattern {
+    /// Compiles `query` as a regular expression if `regex` is set, otherwise it is matched
+    /// literally. The error says what is wrong with an invalid regular expression.
+    pub fn new(query: &str, regex: bool) -> Result<SearchPattern, String> {
+        if !regex {
+            return Ok(SearchPattern::Literal(query.to_string()));
+        }
+
+        match Regex::new(query) {
+            Ok(regex) => Ok(SearchPattern::Regex(regex)),
+            // syntax errors span several lines, the last one says what went wrong
+            Err(e) => {
+                let e = e.to_string();
+                let reason = e.lines().last().unwrap_or("");
+                Err(format!(
+                    "Invalid regex: {}",
+                    reason.trim_start_matches("error: ")
+                ))
+            }
+        }
+    }
+
+    /// Byte ranges of the matches in `line`.
+    pub fn find(&self, line: &str) -> Vec<(usize, usize)> {
+        match self {
+            SearchPattern::Literal(query) => line
+                .match_indices(query.as_str())
+                .map(|(index, text)| (index, index + text.len()))
+                .collect(),
+            SearchPattern::Regex(regex) => regex
+                .find_iter(line)
+                .map(|m| (m.start(), m.end()))
+                .collect(),
+        }
+    }
+
+    /// The first match in `line` that starts at or after byte `from`, along with the text it
+    /// is replaced by. Regex replacements can refer to capture groups as `$1` or `${name}`.
+    pub fn replace_at(
+        &self,
+        line: &str,
+        from: usize,
+        replacement: &str,
+    ) -> Option<(usize, usize, String)> {
+        match self {
+            SearchPattern::Literal(query) => {
+                let start = from + line[from..].find(query.as_str())?;
+                Some((start, start + query.len(), replacement.to_string()))
+            }
+            SearchPattern::Regex(regex) => {
+                let captures = regex.captures_at(line, from)?;
+                let whole = captures.get(0)?;
+                let mut text = String::new();
+                captures.expand(replacement, &mut text);
+                Some((whole.start(), whole.end(), text))
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::row::Row;
+use std::path::Path;
+
+pub const HL_HIGHLIGHT_NUMBERS: u8 = 1 << 0;
+pub const HL_HIGHLIGHT_STRINGS: u8 = 1 << 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Normal,
+    Comment,
+    MlComment,
+    Keyword,
+    Type,
+    String,
+    Number,
+    Match,        // a search match
+    CurrentMatch, // the search match under the cursor
+}
+
+#[derive(Debug)]
+pub struct EditorSyntax {
+    pub filetype: &'static str,
+    pub filematch: &'static [&'static str], // extensions (starting with '.') or whole file names
+    pub keywords: &'static [&'static str],
+    pub types: &'static [&'static str],
+    pub singleline_comment_start: &'static str,
+    pub multiline_comment_start: &'static str,
+    pub multiline_comment_end: &'static str,
+    pub string_delimiters: &'static str,
+    pub flags: u8,
+}
+
+//////////////////// FILETYPES ////////////////////
+
+pub static HLDB: [EditorSyntax; 6] = [
+    EditorSyntax {
+        filetype: "rust",
+        filematch: &[".rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+            "f32", "f64", "bool", "char", "str", "String", "Vec", "Option", "Result", "Box",
+            "Some", "None", "Ok", "Err",
+        ],
+        singleline_comment_start: "//",
+        multiline_comment_start: "/*",
+        multiline_comment_end: "*/",
+        // single quotes are left out so lifetimes don't open a string
+        string_delimiters: "\"",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+    },
+    EditorSyntax {
+        filetype: "c",
+        filematch: &[".c", ".h", ".cpp", ".hpp", ".cc"],
+        keywords: &[
+            "switch", "if", "while", "for", "break", "continue", "return", "else", "struct",
+            "union", "typedef", "static", "enum", "class", "case", "default", "do", "goto",
+            "sizeof", "const", "extern", "volatile", "register", "inline",
+        ],
+        types: &[
+            "int", "long", "double", "float", "char", "unsigned", "signed", "void", "short",
+            "auto", "size_t", "bool",
+        ],
+        singleline_comment_start: "//",
+        multiline_comment_start: "/*",
+        multiline_comment_end: "*/",
+        string_delimiters: "\"'",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+    },
+    EditorSyntax {
+        filetype: "python",
+        filematch: &[".py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield", "None", "True", "False",
+        ],
+        types: &[
+            "int", "float", "str", "bool", "list", "dict", "tuple", "set", "bytes", "object",
+            "self",
+        ],
+        singleline_comment_start: "#",
+        // docstrings are shown as comments
+        multiline_comment_start: "\"\"\"",
+        multiline_comment_end: "\"\"\"",
+        string_delimiters: "\"'",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+    },
+    EditorSyntax {
+        filetype: "shell",
+        filematch: &[".sh", ".bash", ".zsh"],
+        keywords: &[
+            "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until", "do",
+            "done", "in", "function", "return", "local", "export", "break", "continue", "select",
+        ],
+        types: &[
+            "echo", "read", "set", "unset", "shift", "exit", "cd", "eval", "exec", "source",
+            "alias", "test", "printf", "trap",
+        ],
+        singleline_comment_start: "#",
+        multiline_comment_start: "",
+        multiline_comment_end: "",
+        string_delimiters: "\"'",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+    },
+    EditorSyntax {
+        filetype: "toml",
+        filematch: &[".toml", "Cargo.lock"],
+        keywords: &["true", "false"],
+        types: &[],
+        singleline_comment_start: "#",
+        multiline_comment_start: "",
+        multiline_comment_end: "",
+        string_delimiters: "\"'",
+        flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+    },
+    EditorSyntax {
+        filetype: "markdown",
+        filematch: &[".md", ".markdown"],
+        keywords: &[],
+        types: &[],
+        singleline_comment_start: "",
+        multiline_comment_start: "<!--",
+        multiline_comment_end: "-->",
+        // inline code spans
+        string_delimiters: "`",
+        flags: HL_HIGHLIGHT_STRINGS,
+    },
+];
+
+//////////////////// SYNTAX HIGHLIGHTING ////////////////////
+
+fn is_separator(c: u8) -> bool {
+    c.is_ascii_whitespace() || c == b'\0' || b",.()+-/*=~%<>[]{};:&|!^?".contains(&c)
+}
+
+pub(crate) fn is_separator_char(c: char) -> bool {
+    c.is_whitespace() || (c.is_ascii() && is_separator(c as u8))
+}
+
+/// The highlighting rules for a file, picked by its extension or its whole name.
+pub fn select_syntax(filename: &str) -> Option<&'static EditorSyntax> {
+    let path = Path::new(filename);
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let extension = path.extension().and_then(|ext| ext.to_str());
+
+    HLDB.iter().find(|syntax| {
+        syntax
+            .filematch
+            .iter()
+            .any(|pattern| match pattern.strip_prefix('.') {
+                Some(pattern_ext) => extension == Some(pattern_ext),
+                None => name == *pattern,
+            })
+    })
+}
+
+/// Fills `erow.hl` from `erow.render`, `prev_open_comment` being if the row before it ends
+/// inside a multi-line comment.
+pub(crate) fn highlight_row(
+    erow: &mut Row,
+    syntax: Option<&EditorSyntax>,
+    prev_open_comment: bool,
+) {
+    erow.hl.clear();
+    erow.hl.resize(erow.render.len(), Highlight::Normal);
+
+    let syntax = match syntax {
+        Some(syntax) => syntax,
+        None => return,
+    };
+
+    let bytes = erow.render.as_bytes();
+    let scs = syntax.singleline_comment_start.as_bytes();
+    let mcs = syntax.multiline_comment_start.as_bytes();
+    let mce = syntax.multiline_comment_end.as_bytes();
+
+    let mut prev_sep = true;
+    let mut in_string: Option<u8> = None;
+    let mut in_comment = prev_open_comment;
+
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let prev_hl = if i > 0 {
+            erow.hl[i - 1]
+        } else {
+            Highlight::Normal
+        };
+
+        if !scs.is_empty() && in_string.is_none() && !in_comment && bytes[i..].starts_with(scs) {
+            erow.hl[i..].fill(Highlight::Comment);
+            break;
+        }
+
+        if !mcs.is_empty() && !mce.is_empty() && in_string.is_none() {
+            if in_comment {
+                if bytes[i..].starts_with(mce) {
+                    erow.hl[i..i + mce.len()].fill(Highlight::MlComment);
+                    i += mce.len();
+                    in_comment = false;
+                    prev_sep = true;
+                } else {
+                    erow.hl[i] = Highlight::MlComment;
+                    i += 1;
+                }
+                continue;
+            } else if bytes[i..].starts_with(mcs) {
+                erow.hl[i..i + mcs.len()].fill(Highlight::MlComment);
+                i += mcs.len();
+                in_comment = true;
+                continue;
+            }
+        }
+
+        if syntax.flags & HL_HIGHLIGHT_STRINGS != 0 {
+            if let Some(delimiter) = in_string {
+                erow.hl[i] = Highlight::String;
+                // skip over escaped characters
+                if c == b'\\' && i + 1 < bytes.len() {
+                    erow.hl[i + 1] = Highlight::String;
+                    i += 2;
+                    continue;
+                }
+                if c == delimiter {
+                    in_string = None;
+                }
+                i += 1;
+                prev_sep = true;
+                continue;
+            } else if syntax.string_delimiters.as_bytes().contains(&c) {
+                in_string = Some(c);
+                erow.hl[i] = Highlight::String;
+                i += 1;
+                continue;
+            }
+        }
+
+        if syntax.flags & HL_HIGHLIGHT_NUMBERS != 0
+            && ((c.is_ascii_digit() && (prev_sep || prev_hl == Highlight::Number))
+                || (c == b'.' && prev_hl == Highlight::Number))
+        {
+            erow.hl[i] = Highlight::Number;
+            i += 1;
+            prev_sep = false;
+            continue;
+        }
+
+        if prev_sep {
+            if let Some((len, hl)) = match_keyword(&bytes[i..], syntax) {
+                erow.hl[i..i + len].fill(hl);
+                i += len;
+                prev_sep = false;
+                continue;
+            }
+        }
+
+        prev_sep = is_separator(c);
+        i += 1;
+    }
+}
+
+/// If `line` ends inside a multi-line comment, the way `highlight_row` would see it, without
+/// highlighting the rest. Tabs don't matter here, so it works on the line as it is.
+pub(crate) fn ends_in_comment(line: &str, syntax: &EditorSyntax, prev_open_comment: bool) -> bool {
+    let bytes = line.as_bytes();
+    let scs = syntax.singleline_comment_start.as_bytes();
+    let mcs = syntax.multiline_comment_start.as_bytes();
+    let mce = syntax.multiline_comment_end.as_bytes();
+    if mcs.is_empty() || mce.is_empty() {
+        return false;
+    }
+
+    let mut in_string: Option<u8> = None;
+    let mut in_comment = prev_open_comment;
+
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+
+        if in_string.is_none() {
+            if in_comment {
+                if bytes[i..].starts_with(mce) {
+                    i += mce.len();
+                    in_comment = false;
+                } else {
+                    i += 1;
+                }
+                continue;
+            } else if !scs.is_empty() && bytes[i..].starts_with(scs) {
+                break;
+            } else if bytes[i..].starts_with(mcs) {
+                i += mcs.len();
+                in_comment = true;
+                continue;
+            }
+        }
+
+        if syntax.flags & HL_HIGHLIGHT_STRINGS != 0 {
+            if let Some(delimiter) = in_string {
+                // skip over escaped characters
+                if c == b'\\' && i + 1 < bytes.len() {
+                    i += 2;
+                    continue;
+                }
+                if c == delimiter {
+                    in_string = None;
+                }
+            } else if syntax.string_delimiters.as_bytes().contains(&c) {
+                in_string = Some(c);
+            }
+        }
+        i += 1;
+    }
+
+    in_comment
+}
+
+fn match_keyword(text: &[u8], syntax: &EditorSyntax) -> Option<(usize, Highlight)> {
+    let keywords = syntax.keywords.iter().map(|kw| (kw, Highlight::Keyword));
+    let types = syntax.types.iter().map(|kw| (kw, Highlight::Type));
+
+    for (keyword, hl) in keywords.chain(types) {
+        let keyword = keyword.as_bytes();
+        if text.starts_with(keyword) && text.get(keyword.len()).map_or(true, |c| is_separator(*c)) {
+            return Some((keyword.len(), hl));
+        }
+    }
+
+    None
+}
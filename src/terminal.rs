@@ -0,0 +1,196 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::backend::Backend;
+use core::str;
+use libc::{ioctl, winsize, STDIN_FILENO, STDOUT_FILENO, TIOCGWINSZ};
+use std::io::{self, Stdout, Write};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use termios::*;
+
+static WINDOW_RESIZED: AtomicBool = AtomicBool::new(false);
+
+/// The terminal the editor runs in, kept in raw mode for as long as this is alive.
+pub struct Terminal {
+    stdout: Stdout,
+    orig_termios: Termios,
+    size: Option<(usize, usize)>, // remembered until the terminal is resized
+}
+
+impl Terminal {
+    /// Puts the terminal in raw mode and starts listening for it being resized.
+    pub fn new() -> io::Result<Terminal> {
+        let orig_termios = Termios::from_fd(STDIN_FILENO)?;
+        enable_raw_mode(STDIN_FILENO)?;
+        install_resize_handler();
+
+        Ok(Terminal {
+            stdout: io::stdout(),
+            orig_termios,
+            size: None,
+        })
+    }
+
+    fn get_window_size_from_cursor(&mut self) -> io::Result<(usize, usize)> {
+        // send cursor to bottom right
+        self.stdout.write_all(b"\x1b[999C\x1b[999B")?;
+        self.stdout.flush()?;
+
+        let mut buffer = [0u8; 32];
+
+        // request cursor cordinates
+        self.stdout.write_all(b"\x1b[6n")?;
+        self.stdout.flush()?;
+
+        let len = read_stdin(&mut buffer)?;
+        let reply = str::from_utf8(&buffer[..len]).unwrap_or("");
+        let size = reply
+            .strip_prefix("\x1b[")
+            .and_then(|reply| reply.strip_suffix('R'))
+            .and_then(|reply| reply.split_once(';'))
+            .and_then(|(rows, cols)| Some((rows.parse().ok()?, cols.parse().ok()?)));
+
+        size.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no cursor position"))
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // ansi screen clear code and cursor home code
+        let _ = self.stdout.write_all(b"\x1b[2J\x1b[H");
+        let _ = self.stdout.flush();
+
+        disable_raw_mode(STDIN_FILENO, &self.orig_termios);
+    }
+}
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+impl Backend for Terminal {
+    fn read_input(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // the resize handler interrupts the read so the screen can be redrawn right away, this
+        // catches a resize that came in before the read started
+        if WINDOW_RESIZED.load(Ordering::Relaxed) {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        read_stdin(buf)
+    }
+
+    fn poll_input(&mut self, timeout: Duration) -> io::Result<bool> {
+        let mut fds = libc::pollfd {
+            fd: STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        match unsafe { libc::poll(&mut fds, 1, timeout) } {
+            -1 => Err(io::Error::last_os_error()),
+            ready => Ok(ready > 0),
+        }
+    }
+
+    fn size(&mut self) -> io::Result<(usize, usize)> {
+        if WINDOW_RESIZED.swap(false, Ordering::Relaxed) {
+            self.size = None;
+        }
+        if let Some(size) = self.size {
+            return Ok(size);
+        }
+
+        let ws = winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+
+        let size = if unsafe { ioctl(STDOUT_FILENO, TIOCGWINSZ, &ws) == -1 } || ws.ws_col == 0 {
+            self.get_window_size_from_cursor()?
+        } else {
+            (ws.ws_row as usize, ws.ws_col as usize)
+        };
+        self.size = Some(size);
+        Ok(size)
+    }
+}
+
+/// Reads straight from the file descriptor, the buffering `Stdin` does would keep `poll_input`
+/// from seeing input that was already read.
+fn read_stdin(buf: &mut [u8]) -> io::Result<usize> {
+    let len = unsafe { libc::read(STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(len as usize)
+}
+
+fn enable_raw_mode(stdin_fd: i32) -> io::Result<()> {
+    let mut termios = Termios::from_fd(stdin_fd)?;
+
+    // specs can be found here
+    // https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/termios.h.html
+    termios.c_iflag &= !(BRKINT | INPCK | ISTRIP | IXON | ICRNL);
+    termios.c_oflag &= !(OPOST);
+    termios.c_cflag |= CS8;
+    termios.c_lflag &= !(ICANON | ECHO | ISIG | IEXTEN);
+    tcsetattr(stdin_fd, TCSAFLUSH, &termios)?;
+
+    // bracketed paste mode, pasted text comes in between markers instead of as typed keys
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[?2004h")?;
+    // report mouse clicks, drags and the wheel, with SGR encoded coordinates
+    stdout.write_all(b"\x1b[?1000h\x1b[?1002h\x1b[?1006h")?;
+    stdout.flush()
+}
+
+fn disable_raw_mode(stdin_fd: i32, orig_termios: &Termios) {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(b"\x1b[?1006l\x1b[?1002l\x1b[?1000l");
+    let _ = stdout.write_all(b"\x1b[?2004l");
+    let _ = stdout.flush();
+
+    let _ = tcsetattr(stdin_fd, TCSAFLUSH, orig_termios);
+}
+
+extern "C" fn handle_sigwinch(_: libc::c_int) {
+    WINDOW_RESIZED.store(true, Ordering::Relaxed);
+}
+
+/// Catches SIGWINCH without SA_RESTART, so a pending read returns `Interrupted`.
+fn install_resize_handler() {
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handle_sigwinch as *const () as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGWINCH, &action, ptr::null_mut());
+    }
+}
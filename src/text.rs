@@ -0,0 +1,209 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::file::{encode_line, LineEnding};
+use crate::mapped::{MappedFile, MappedText};
+use ropey::{Rope, RopeBuilder, RopeSlice};
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// The lines of a buffer. Files that are read in are kept in a rope so that inserting and
+/// deleting anywhere takes O(log n) time, every line being followed by '\n' in it. Huge files
+/// are mapped instead, with the edits kept apart from them.
+#[derive(Debug)]
+pub(crate) enum Text {
+    Rope(Rope),
+    Mapped(MappedText),
+}
+
+impl Default for Text {
+    fn default() -> Text {
+        Text::Rope(Rope::new())
+    }
+}
+
+impl Text {
+    pub(crate) fn new() -> Text {
+        Text::default()
+    }
+
+    pub(crate) fn from_lines(lines: impl IntoIterator<Item = String>) -> Text {
+        let mut builder = RopeBuilder::new();
+        for line in lines {
+            builder.append(&line);
+            builder.append("\n");
+        }
+        Text::Rope(builder.finish())
+    }
+
+    pub(crate) fn mapped(file: MappedFile) -> Text {
+        Text::Mapped(MappedText::new(file))
+    }
+
+    /// If more lines are still being found in a mapped file.
+    pub(crate) fn is_loading(&self) -> bool {
+        match self {
+            Text::Rope(_) => false,
+            Text::Mapped(text) => text.is_loading(),
+        }
+    }
+
+    /// Waits until line `at` of a mapped file is found, unless the file turns out to have
+    /// fewer lines.
+    pub(crate) fn wait_for_line(&self, at: usize) {
+        if let Text::Mapped(text) = self {
+            text.wait_for_line(at);
+        }
+    }
+
+    /// How the lines of a mapped file are terminated, as far as it is indexed. A text that
+    /// was read in doesn't keep its line ending, so that's LF.
+    pub(crate) fn line_ending(&self) -> LineEnding {
+        match self {
+            Text::Rope(_) => LineEnding::Lf,
+            Text::Mapped(text) => text.line_ending(),
+        }
+    }
+
+    /// Like `line_ending`, but waits until it is known for all of a mapped file.
+    pub(crate) fn wait_for_line_ending(&self) -> LineEnding {
+        match self {
+            Text::Rope(_) => LineEnding::Lf,
+            Text::Mapped(text) => text.wait_for_line_ending(),
+        }
+    }
+
+    /// If the lines are read from `filename` as they are needed, so it can't be overwritten
+    /// while they are.
+    pub(crate) fn is_mapped_from(&self, filename: &str) -> bool {
+        match self {
+            Text::Rope(_) => false,
+            Text::Mapped(text) => text.is_mapped_from(filename),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            // the rope counts the empty line after the last '\n' too
+            Text::Rope(rope) => rope.len_lines() - 1,
+            Text::Mapped(text) => text.len(),
+        }
+    }
+
+    /// Line `at`, without the '\n'. Only copied if it is split across chunks of the rope.
+    pub(crate) fn line(&self, at: usize) -> Cow<'_, str> {
+        match self {
+            Text::Rope(rope) => strip_newline(rope.line(at)),
+            Text::Mapped(text) => text.line(at),
+        }
+    }
+
+    pub(crate) fn lines(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        self.lines_from(0)
+    }
+
+    /// The lines from line `at` on, faster than looking each of them up.
+    pub(crate) fn lines_from(&self, at: usize) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        match self {
+            Text::Rope(rope) => Box::new(
+                rope.lines_at(at.min(self.len()))
+                    .take(self.len().saturating_sub(at))
+                    .map(strip_newline),
+            ),
+            Text::Mapped(text) => Box::new(text.lines_from(at)),
+        }
+    }
+
+    /// Inserts `lines` before line `at`, which may be one past the last line.
+    pub(crate) fn insert_lines(&mut self, at: usize, lines: &[String]) {
+        match self {
+            Text::Rope(rope) => {
+                let mut text = String::with_capacity(lines.iter().map(|line| line.len() + 1).sum());
+                for line in lines {
+                    text.push_str(line);
+                    text.push('\n');
+                }
+                rope.insert(rope.line_to_char(at), &text);
+            }
+            Text::Mapped(text) => text.insert_lines(at, lines),
+        }
+    }
+
+    pub(crate) fn remove_lines(&mut self, at: usize, count: usize) {
+        match self {
+            Text::Rope(rope) => {
+                let start = rope.line_to_char(at);
+                let end = rope.line_to_char(at + count);
+                rope.remove(start..end);
+            }
+            Text::Mapped(text) => text.remove_lines(at, count),
+        }
+    }
+
+    pub(crate) fn set_line(&mut self, at: usize, line: &str) {
+        match self {
+            Text::Rope(rope) => {
+                let start = rope.line_to_char(at);
+                let end = rope.line_to_char(at + 1) - 1;
+                rope.remove(start..end);
+                rope.insert(start, line);
+            }
+            Text::Mapped(text) => text.set_line(at, line),
+        }
+    }
+
+    /// Writes the lines to `out` the way they are saved, each terminated by `line_ending`
+    /// except the last one if `final_newline` is false.
+    pub(crate) fn write_to(
+        &self,
+        out: &mut dyn Write,
+        line_ending: LineEnding,
+        final_newline: bool,
+    ) -> io::Result<()> {
+        match self {
+            Text::Rope(_) => {
+                let mut buf = Vec::new();
+                for (i, line) in self.lines().enumerate() {
+                    buf.clear();
+                    encode_line(&line, &mut buf);
+                    if i + 1 < self.len() || final_newline {
+                        buf.extend_from_slice(line_ending.as_bytes());
+                    }
+                    out.write_all(&buf)?;
+                }
+                Ok(())
+            }
+            Text::Mapped(text) => text.write_to(out, line_ending, final_newline),
+        }
+    }
+}
+
+fn strip_newline(line: RopeSlice<'_>) -> Cow<'_, str> {
+    match Cow::from(line) {
+        Cow::Borrowed(line) => Cow::Borrowed(line.strip_suffix('\n').unwrap_or(line)),
+        Cow::Owned(mut line) => {
+            if line.ends_with('\n') {
+                line.pop();
+            }
+            Cow::Owned(line)
+        }
+    }
+}
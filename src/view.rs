@@ -0,0 +1,488 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::buffer::Buffer;
+use crate::goto::GotoTarget;
+use crate::row::{cursorx_to_renderx, line_len, renderx_to_cursorx, wrap_points, Row};
+
+/// Where the cursor is in a buffer and which part of the buffer is on screen.
+#[derive(Debug, Default)]
+pub struct View {
+    pub cursor_x: usize,      // x coordinate of the cursor in the file, in grapheme clusters
+    pub cursor_y: usize,      // y coordinate of the cursor in the file
+    pub render_x: usize,      // x coordinate of the render
+    pub row_offset: usize,    // keeps track of what row you are on
+    pub column_offset: usize, // keeps track of what column you are on
+    pub wrap_offset: usize,   // wrapped lines of the top row that are above the screen
+    pub screen_rows: usize,   // how many rows the screen can display
+    pub screen_cols: usize,   // how many columns of text the screen can display
+    pub gutter_width: usize,  // columns left of the text taken by line numbers
+    pub wrap: bool,           // if long rows are wrapped instead of scrolled sideways
+    pub line_numbers: LineNumbers,
+    // the end of the selection the cursor isn't at, None if nothing is selected
+    pub anchor: Option<(usize, usize)>,
+}
+
+/// How rows are numbered in the gutter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineNumbers {
+    /// No gutter.
+    #[default]
+    Off,
+    /// The number of each row in the file.
+    Absolute,
+    /// How many rows each row is away from the cursor.
+    Relative,
+    /// Relative, except for the cursor's row, which gets its number in the file.
+    Hybrid,
+}
+
+impl LineNumbers {
+    /// Parses the `off`, `absolute`, `relative` and `hybrid` names used to configure the mode.
+    pub fn from_name(name: &str) -> Option<LineNumbers> {
+        match name {
+            "off" => Some(LineNumbers::Off),
+            "absolute" => Some(LineNumbers::Absolute),
+            "relative" => Some(LineNumbers::Relative),
+            "hybrid" => Some(LineNumbers::Hybrid),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineNumbers::Off => "off",
+            LineNumbers::Absolute => "absolute",
+            LineNumbers::Relative => "relative",
+            LineNumbers::Hybrid => "hybrid",
+        }
+    }
+
+    /// The mode after this one when cycling through them.
+    pub fn next(self) -> LineNumbers {
+        match self {
+            LineNumbers::Off => LineNumbers::Absolute,
+            LineNumbers::Absolute => LineNumbers::Relative,
+            LineNumbers::Relative => LineNumbers::Hybrid,
+            LineNumbers::Hybrid => LineNumbers::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl View {
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_x, self.cursor_y)
+    }
+
+    pub fn set_cursor(&mut self, cursor: (usize, usize)) {
+        (self.cursor_x, self.cursor_y) = cursor;
+    }
+
+    /// Updates `render_x` and scrolls just far enough for the cursor to be on screen.
+    pub fn scroll(&mut self, buffer: &Buffer) {
+        self.render_x = 0;
+        if let Some(line) = buffer.line(self.cursor_y) {
+            self.render_x = cursorx_to_renderx(&line, self.cursor_x, buffer.tab_stop());
+        }
+
+        if self.wrap {
+            self.scroll_wrapped(buffer);
+            return;
+        }
+
+        if self.cursor_y < self.row_offset {
+            self.row_offset = self.cursor_y;
+        }
+
+        if self.cursor_y >= self.row_offset + self.screen_rows {
+            self.row_offset = self.cursor_y + 1 - self.screen_rows;
+        }
+
+        if self.render_x < self.column_offset {
+            self.column_offset = self.render_x;
+        }
+
+        if self.render_x >= self.column_offset + self.screen_cols {
+            self.column_offset = self.render_x + 1 - self.screen_cols;
+        }
+    }
+
+    /// Splits `cols` screen columns between the gutter and the text. The gutter is as wide as
+    /// the last row's number plus a space, and is left out if it would leave no room for text.
+    pub fn set_screen_cols(&mut self, buffer: &Buffer, cols: usize) {
+        self.gutter_width = match self.line_numbers {
+            LineNumbers::Off => 0,
+            _ => buffer.num_rows().max(1).to_string().len() + 1,
+        };
+        if self.gutter_width >= cols {
+            self.gutter_width = 0;
+        }
+        self.screen_cols = (cols - self.gutter_width).max(1);
+    }
+
+    /// The number shown in the gutter for `row`, None if there's no gutter.
+    pub fn line_number(&self, row: usize) -> Option<usize> {
+        if self.gutter_width == 0 {
+            return None;
+        }
+        match self.line_numbers {
+            LineNumbers::Off => None,
+            LineNumbers::Absolute => Some(row + 1),
+            LineNumbers::Hybrid if row == self.cursor_y => Some(row + 1),
+            LineNumbers::Relative | LineNumbers::Hybrid => Some(row.abs_diff(self.cursor_y)),
+        }
+    }
+
+    /// Scrolls by wrapped lines rather than rows, and never sideways.
+    fn scroll_wrapped(&mut self, buffer: &Buffer) {
+        self.column_offset = 0;
+        // an edit can leave the top row with fewer wrapped lines than before
+        let top_len = self.wrapped_len(buffer, self.row_offset);
+        let top = (self.row_offset, self.wrap_offset.min(top_len - 1));
+        let (cursor, _) = self.wrapped_cursor(buffer);
+        let lowest_top = self.wrapped_up(buffer, cursor, self.screen_rows.saturating_sub(1));
+        (self.row_offset, self.wrap_offset) = if cursor < top {
+            cursor
+        } else {
+            top.max(lowest_top)
+        };
+    }
+
+    /// Moves the cursor to `target` and scrolls it to the middle of the screen.
+    pub fn goto(&mut self, buffer: &Buffer, target: GotoTarget) {
+        let cursor = target.resolve(buffer, self.cursor_y);
+        self.set_cursor(cursor);
+        self.center_cursor(buffer);
+    }
+
+    /// Scrolls so that the cursor's row is in the middle of the screen.
+    pub fn center_cursor(&mut self, buffer: &Buffer) {
+        if self.wrap {
+            let (cursor, _) = self.wrapped_cursor(buffer);
+            (self.row_offset, self.wrap_offset) =
+                self.wrapped_up(buffer, cursor, self.screen_rows / 2);
+        } else {
+            self.row_offset = self.cursor_y.saturating_sub(self.screen_rows / 2);
+        }
+    }
+
+    /// Scrolls the screen by `lines` rows, down if positive, and brings the cursor along if it
+    /// would go off screen.
+    pub fn scroll_by(&mut self, buffer: &Buffer, lines: isize) {
+        if self.wrap {
+            self.scroll_wrapped_by(buffer, lines);
+            return;
+        }
+
+        let last_row = buffer.num_rows().saturating_sub(1);
+        self.row_offset = self.row_offset.saturating_add_signed(lines).min(last_row);
+
+        let last_visible = self.row_offset + self.screen_rows.saturating_sub(1);
+        self.cursor_y = self.cursor_y.clamp(self.row_offset, last_visible);
+        self.snap_cursor(buffer);
+    }
+
+    fn scroll_wrapped_by(&mut self, buffer: &Buffer, lines: isize) {
+        let top = (self.row_offset, self.wrap_offset);
+        let last_row = buffer.num_rows().saturating_sub(1);
+        let top = if lines < 0 {
+            self.wrapped_up(buffer, top, lines.unsigned_abs())
+        } else {
+            self.wrapped_down(buffer, top, lines.unsigned_abs(), last_row)
+        };
+        (self.row_offset, self.wrap_offset) = top;
+
+        let bottom = self.wrapped_down(
+            buffer,
+            top,
+            self.screen_rows.saturating_sub(1),
+            buffer.num_rows(),
+        );
+        let (cursor, col) = self.wrapped_cursor(buffer);
+        if cursor < top {
+            self.set_cursor(self.wrapped_to_cursor(buffer, top, col));
+        } else if cursor > bottom {
+            self.set_cursor(self.wrapped_to_cursor(buffer, bottom, col));
+        }
+    }
+
+    /// Moves the cursor to `row` of the screen. With wrapping, it keeps its column.
+    pub fn move_to_screen_row(&mut self, buffer: &Buffer, row: usize) {
+        if self.wrap {
+            let (_, col) = self.wrapped_cursor(buffer);
+            let top = (self.row_offset, self.wrap_offset);
+            let line = self.wrapped_down(buffer, top, row, buffer.num_rows());
+            self.set_cursor(self.wrapped_to_cursor(buffer, line, col));
+        } else {
+            self.cursor_y = self.row_offset + row;
+        }
+    }
+
+    /// The position in the buffer drawn at `row` and `col` of the screen, clamped to the text.
+    /// Clicking the gutter lands at the start of the row.
+    pub fn screen_to_cursor(&self, buffer: &Buffer, row: usize, col: usize) -> (usize, usize) {
+        let col = col.saturating_sub(self.gutter_width);
+        if self.wrap {
+            let top = (self.row_offset, self.wrap_offset);
+            let last_row = buffer.num_rows().saturating_sub(1);
+            let line = self.wrapped_down(buffer, top, row, last_row);
+            return self.wrapped_to_cursor(buffer, line, col);
+        }
+
+        let y = (self.row_offset + row).min(buffer.num_rows().saturating_sub(1));
+        let x = buffer.line(y).map_or(0, |line| {
+            renderx_to_cursorx(&line, self.column_offset + col, buffer.tab_stop())
+        });
+        (x, y)
+    }
+
+    /// The start and end of the selected text, None if nothing is selected.
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.anchor?;
+        let cursor = self.cursor();
+        // ordered by row first
+        let (start, end) = if (anchor.1, anchor.0) < (cursor.1, cursor.0) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        (start != end).then_some((start, end))
+    }
+
+    pub fn is_cursor_visible(&self, buffer: &Buffer) -> bool {
+        if !self.wrap {
+            return self.cursor_y >= self.row_offset
+                && self.cursor_y < self.row_offset + self.screen_rows;
+        }
+
+        let top = (self.row_offset, self.wrap_offset);
+        let bottom = self.wrapped_down(
+            buffer,
+            top,
+            self.screen_rows.saturating_sub(1),
+            buffer.num_rows(),
+        );
+        let (cursor, _) = self.wrapped_cursor(buffer);
+        top <= cursor && cursor <= bottom
+    }
+
+    /// The row and column of the text area the cursor is drawn at, once `scroll` has put it
+    /// on screen.
+    pub fn cursor_on_screen(&self, buffer: &Buffer) -> (usize, usize) {
+        if !self.wrap {
+            return (
+                self.cursor_y - self.row_offset,
+                self.render_x - self.column_offset,
+            );
+        }
+
+        let (cursor, col) = self.wrapped_cursor(buffer);
+        let mut line = (self.row_offset, self.wrap_offset);
+        let mut y = 0;
+        while line < cursor && y < self.screen_rows {
+            line = self.wrapped_down(buffer, line, 1, cursor.0);
+            y += 1;
+        }
+        (y, col)
+    }
+
+    /// The row drawn on each line of the screen, with the render columns it's drawn from and
+    /// up to. Rows past the end of the buffer are left out when wrapping.
+    pub fn screen_lines(&self, buffer: &Buffer) -> Vec<(usize, usize, usize)> {
+        if !self.wrap {
+            let (first_col, last_col) = (self.column_offset, self.column_offset + self.screen_cols);
+            return (0..self.screen_rows)
+                .map(|y| (self.row_offset + y, first_col, last_col))
+                .collect();
+        }
+
+        let mut lines = Vec::with_capacity(self.screen_rows);
+        let mut skip = self.wrap_offset;
+        let mut row = self.row_offset;
+        while lines.len() < self.screen_rows && row < buffer.num_rows() {
+            let points = self.row_wrap_points(buffer, row);
+            for (i, &start) in points.iter().enumerate().skip(skip) {
+                let end = points
+                    .get(i + 1)
+                    .map_or(start + self.screen_cols, |&end| end);
+                lines.push((row, start, end));
+            }
+            skip = 0;
+            row += 1;
+        }
+        lines.truncate(self.screen_rows);
+        lines
+    }
+
+    /// Moves the cursor by one grapheme cluster or row. Moving left or right past either end
+    /// of a row continues on the row before or after it.
+    pub fn move_cursor(&mut self, buffer: &Buffer, direction: Direction) {
+        let (cx, cy) = (self.cursor_x, self.cursor_y);
+        let row_len = buffer.line(cy).map(|line| line_len(&line));
+
+        match direction {
+            Direction::Up | Direction::Down if self.wrap => {
+                self.move_wrapped(buffer, direction);
+            }
+            Direction::Up if cy != 0 => {
+                self.cursor_y -= 1;
+            }
+            Direction::Left => {
+                if cx != 0 {
+                    self.cursor_x -= 1
+                } else if cy > 0 {
+                    self.cursor_y -= 1;
+                    self.cursor_x = buffer.line(self.cursor_y).map_or(0, |line| line_len(&line));
+                }
+            }
+            Direction::Down if cy < buffer.num_rows() => {
+                self.cursor_y += 1;
+            }
+            Direction::Right => {
+                if row_len.is_some_and(|len| cx < len) {
+                    self.cursor_x += 1
+                } else if row_len == Some(cx) {
+                    self.cursor_y += 1;
+                    self.cursor_x = 0;
+                }
+            }
+            _ => (),
+        }
+
+        self.snap_cursor(buffer);
+    }
+
+    /// Moves the cursor up or down a wrapped line, keeping its column where the line is long
+    /// enough.
+    fn move_wrapped(&mut self, buffer: &Buffer, direction: Direction) {
+        let (cursor, col) = self.wrapped_cursor(buffer);
+        let line = if direction == Direction::Up {
+            self.wrapped_up(buffer, cursor, 1)
+        } else {
+            self.wrapped_down(buffer, cursor, 1, buffer.num_rows())
+        };
+        if line != cursor {
+            self.set_cursor(self.wrapped_to_cursor(buffer, line, col));
+        }
+    }
+
+    //////////////////// WRAPPED LINES ////////////////////
+    // A wrapped line is given as (row, index of the line within the row).
+
+    /// The render columns where `row` is broken into wrapped lines.
+    fn row_wrap_points(&self, buffer: &Buffer, row: usize) -> Vec<usize> {
+        match buffer.line(row) {
+            Some(line) => wrap_points(
+                Row::new(line.into_owned(), buffer.tab_stop()).render(),
+                self.screen_cols,
+            ),
+            None => vec![0],
+        }
+    }
+
+    fn wrapped_len(&self, buffer: &Buffer, row: usize) -> usize {
+        self.row_wrap_points(buffer, row).len()
+    }
+
+    /// The wrapped line the cursor is on and its column within that line.
+    fn wrapped_cursor(&self, buffer: &Buffer) -> ((usize, usize), usize) {
+        let rx = buffer.line(self.cursor_y).map_or(0, |line| {
+            cursorx_to_renderx(&line, self.cursor_x, buffer.tab_stop())
+        });
+        let points = self.row_wrap_points(buffer, self.cursor_y);
+        let i = points.partition_point(|&point| point <= rx) - 1;
+        ((self.cursor_y, i), rx - points[i])
+    }
+
+    /// The cursor position at `col` of a wrapped line, or at its end if the line is shorter.
+    fn wrapped_to_cursor(
+        &self,
+        buffer: &Buffer,
+        line: (usize, usize),
+        col: usize,
+    ) -> (usize, usize) {
+        let (row, i) = line;
+        let text = buffer.line(row).unwrap_or_default();
+        let points = self.row_wrap_points(buffer, row);
+        let i = i.min(points.len() - 1);
+        let mut rx = points[i] + col;
+        if let Some(next) = points.get(i + 1) {
+            rx = rx.min(next - 1);
+        }
+        (renderx_to_cursorx(&text, rx, buffer.tab_stop()), row)
+    }
+
+    /// The wrapped line `count` lines above `line`, stopping at the top of the buffer.
+    fn wrapped_up(&self, buffer: &Buffer, line: (usize, usize), count: usize) -> (usize, usize) {
+        let (mut row, mut i) = line;
+        for _ in 0..count {
+            if i > 0 {
+                i -= 1;
+            } else if row > 0 {
+                row -= 1;
+                i = self.wrapped_len(buffer, row) - 1;
+            } else {
+                break;
+            }
+        }
+        (row, i)
+    }
+
+    /// The wrapped line `count` lines below `line`, stopping at the last line of `last_row`.
+    fn wrapped_down(
+        &self,
+        buffer: &Buffer,
+        line: (usize, usize),
+        count: usize,
+        last_row: usize,
+    ) -> (usize, usize) {
+        let (mut row, mut i) = line;
+        let mut len = self.wrapped_len(buffer, row);
+        for _ in 0..count {
+            if i + 1 < len {
+                i += 1;
+            } else if row < last_row {
+                row += 1;
+                i = 0;
+                len = self.wrapped_len(buffer, row);
+            } else {
+                break;
+            }
+        }
+        (row, i)
+    }
+
+    /// Snaps to the end of the row the cursor ended up on.
+    fn snap_cursor(&mut self, buffer: &Buffer) {
+        let row_len = buffer.line(self.cursor_y).map_or(0, |line| line_len(&line));
+        if self.cursor_x > row_len {
+            self.cursor_x = row_len
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// * Redistributions of source code must retain the above copyright notice,
+//   this list of conditions and the following disclaimer.
+//
+// * Redistributions in binary form must reproduce the above copyright notice,
+//   this list of conditions and the following disclaimer in the documentation
+//   and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use ronto::{Buffer, Editor, VirtualScreen};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A directory for the files of one test, removed with them when the test ends, even if it
+/// fails.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("ronto-editor-test-{}-{name}", process::id()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn file(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // a failed test is already being reported, not being able to clean up isn't news
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn editor(path: &Path, rows: usize, cols: usize) -> Editor<VirtualScreen> {
+    let buffer = Buffer::open(path.to_str().unwrap()).unwrap();
+    let mut editor = Editor::new(buffer, VirtualScreen::new(rows, cols));
+    editor.refresh_screen();
+    editor
+}
+
+/// Pushes `input` and handles keypresses until all of it has been read.
+fn feed(editor: &mut Editor<VirtualScreen>, input: &[u8], keypresses: usize) {
+    editor.backend_mut().push_input(input);
+    for _ in 0..keypresses {
+        editor.process_keypress();
+    }
+    editor.refresh_screen();
+}
+
+fn row(editor: &Editor<VirtualScreen>, at: usize) -> String {
+    editor.backend().row_text(at).trim_end().to_string()
+}
+
+#[test]
+fn typing_is_drawn() {
+    let dir = TempDir::new("typing");
+    let path = dir.file("typing.txt", "hello world\nsecond line\n");
+    let mut editor = editor(&path, 8, 40);
+    assert_eq!(row(&editor, 0), "hello world");
+    assert_eq!(row(&editor, 1), "second line");
+    assert_eq!(row(&editor, 2), "~");
+
+    feed(&mut editor, b"abc", 3);
+    assert_eq!(row(&editor, 0), "abchello world");
+    assert_eq!(editor.backend().cursor(), (0, 3));
+    assert!(row(&editor, 6).contains("(modified"));
+}
+
+#[test]
+fn save_and_undo() {
+    let dir = TempDir::new("save");
+    let path = dir.file("save.txt", "one\ntwo\n");
+    let mut editor = editor(&path, 8, 40);
+
+    feed(&mut editor, b"\x1b[Bx", 2);
+    assert_eq!(row(&editor, 1), "xtwo");
+
+    feed(&mut editor, b"\x13", 1);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "one\nxtwo\n");
+    assert!(!editor.buffer().is_dirty());
+    assert!(!row(&editor, 6).contains("(modified"));
+
+    feed(&mut editor, b"\x1a", 1);
+    assert_eq!(row(&editor, 1), "two");
+    assert!(editor.buffer().is_dirty());
+
+    feed(&mut editor, b"\x13", 1);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+}
+
+#[test]
+fn a_failed_save_as_keeps_the_old_name() {
+    let dir = TempDir::new("save-as");
+    let path = dir.file("save-as.txt", "text\n");
+    let mut editor = editor(&path, 8, 60);
+    let name = path.to_str().unwrap().to_string();
+
+    feed(&mut editor, b"x\x10saveas /nonexistent/dir/file.txt\r", 2);
+    assert!(row(&editor, 7).starts_with("Can't save!"));
+    assert_eq!(editor.buffer().filename(), name);
+
+    let other = path.with_file_name("saved-as.txt");
+    let command = format!("\x10saveas {}\r", other.display());
+    feed(&mut editor, command.as_bytes(), 1);
+    assert_eq!(editor.buffer().filename(), other.to_str().unwrap());
+    assert_eq!(fs::read_to_string(&other).unwrap(), "xtext\n");
+}
+
+#[test]
+fn search_highlights_the_matches() {
+    let dir = TempDir::new("search");
+    let path = dir.file("search.txt", "hello world\nsecond line\nline three\n");
+    let mut editor = editor(&path, 8, 40);
+
+    feed(&mut editor, b"\x06line\r", 1);
+    let screen = editor.backend();
+    let highlighted = |row: usize, col: usize| screen.cell(row, col).style.bg.is_some();
+    assert!((7..11).all(|col| highlighted(1, col)));
+    assert!(!highlighted(1, 6) && !highlighted(1, 11));
+    assert!((0..4).all(|col| highlighted(2, col)));
+    assert!(!(0..11).any(|col| highlighted(0, col)));
+    assert_eq!(screen.cursor(), (1, 7));
+
+    feed(&mut editor, b"\x1b", 1);
+    let screen = editor.backend();
+    assert!(!(0..11).any(|col| screen.cell(1, col).style.bg.is_some()));
+}
+
+#[test]
+fn replace_asks_for_each_match() {
+    let dir = TempDir::new("replace");
+    let path = dir.file("replace.txt", "a a a a a\n");
+    let mut editor = editor(&path, 8, 40);
+
+    feed(&mut editor, b"\x12a\rb\rynyq", 1);
+    assert_eq!(row(&editor, 0), "b a b a a");
+    assert_eq!(row(&editor, 7), "Replaced 2 occurrence(s)");
+
+    // each answered match is an edit of its own
+    feed(&mut editor, b"\x1a", 1);
+    assert_eq!(row(&editor, 0), "b a a a a");
+}
+
+#[test]
+fn replace_all_wraps_around_to_the_cursor() {
+    let dir = TempDir::new("replace-all");
+    let path = dir.file("replace-all.txt", "x x x\nx\n");
+    let mut editor = editor(&path, 8, 40);
+
+    // from the last 'x' of the first row, which moves right as the ones before it get longer
+    feed(&mut editor, b"\x1b[C\x1b[C\x1b[C\x1b[C", 4);
+    feed(&mut editor, b"\x12x\ryyy\ra", 1);
+    assert_eq!(row(&editor, 0), "yyy yyy yyy");
+    assert_eq!(row(&editor, 1), "yyy");
+    assert_eq!(row(&editor, 7), "Replaced 4 occurrence(s)");
+
+    feed(&mut editor, b"\x1a", 1);
+    assert_eq!(row(&editor, 0), "x x x");
+    assert_eq!(row(&editor, 1), "x");
+}
+
+#[test]
+fn replace_all_after_answering_some_undoes_the_rest_in_one_go() {
+    let dir = TempDir::new("replace-rest");
+    let path = dir.file("replace-rest.txt", "one two one two one\n");
+    let mut editor = editor(&path, 8, 40);
+
+    feed(&mut editor, b"\x12one\r1\rna", 1);
+    assert_eq!(row(&editor, 0), "one two 1 two 1");
+
+    feed(&mut editor, b"\x1a", 1);
+    assert_eq!(row(&editor, 0), "one two one two one");
+    assert!(!editor.buffer().is_dirty());
+}
+
+#[test]
+fn replace_skips_empty_matches_right_after_a_match() {
+    let dir = TempDir::new("replace-empty");
+    let path = dir.file("replace-empty.txt", "abc\n");
+    let mut editor = editor(&path, 8, 40);
+
+    // ctrl-t makes the query a regex, which matches the 'b' and the empty strings around it
+    feed(&mut editor, b"\x12\x14b*\r-\ra", 1);
+    assert_eq!(row(&editor, 0), "-a-c-");
+    assert_eq!(row(&editor, 7), "Replaced 3 occurrence(s)");
+}
+
+#[test]
+fn copy_puts_the_selection_on_the_clipboard() {
+    let dir = TempDir::new("copy");
+    let path = dir.file("copy.txt", "hello world\nsecond line\n");
+    let mut editor = editor(&path, 8, 40);
+    assert_eq!(editor.backend().clipboard(), None);
+
+    // shift-right five times, then ctrl-c
+    feed(&mut editor, &b"\x1b[1;2C".repeat(5), 5);
+    feed(&mut editor, b"\x03", 1);
+    assert_eq!(editor.backend().clipboard(), Some("hello"));
+    assert_eq!(row(&editor, 7), "5 bytes copied");
+
+    // the selection is still there to copy more of, across rows
+    feed(&mut editor, b"\x1b[1;2B\x03", 2);
+    assert_eq!(editor.backend().clipboard(), Some("hello world\nsecon"));
+
+    // and what was copied is what ctrl-v pastes
+    feed(&mut editor, b"\x1b[F\x16", 2);
+    assert_eq!(row(&editor, 1), "second linehello world");
+    assert_eq!(row(&editor, 2), "secon");
+}
+
+#[test]
+fn bracketed_paste_is_inserted_as_text() {
+    let dir = TempDir::new("paste");
+    let path = dir.file("paste.txt", "end\n");
+    let mut editor = editor(&path, 8, 40);
+
+    feed(&mut editor, b"\x1b[200~first\rsecond\x11\n\x1b[201~", 1);
+    assert_eq!(row(&editor, 0), "first");
+    assert_eq!(row(&editor, 1), "second");
+    assert_eq!(row(&editor, 2), "end");
+    assert!(!editor.should_quit());
+}
+
+#[test]
+fn bracketed_paste_replaces_the_selection() {
+    let dir = TempDir::new("paste-selection");
+    let path = dir.file("paste-selection.txt", "abcd\n");
+    let mut editor = editor(&path, 8, 40);
+
+    // shift-right twice selects "ab"
+    feed(&mut editor, b"\x1b[1;2C\x1b[1;2C", 2);
+    feed(&mut editor, b"\x1b[200~XYZ\x1b[201~", 1);
+    assert_eq!(row(&editor, 0), "XYZcd");
+
+    // the selection is gone, so this only deletes the 'Z'
+    feed(&mut editor, b"\x7f", 1);
+    assert_eq!(row(&editor, 0), "XYcd");
+
+    feed(&mut editor, b"\x1a\x1a", 2);
+    assert_eq!(row(&editor, 0), "abcd");
+}
+
+#[test]
+fn keys_are_bound_from_the_prompt() {
+    let dir = TempDir::new("bind");
+    let path = dir.file("bind.txt", "text\n");
+    let mut editor = editor(&path, 8, 40);
+
+    feed(&mut editor, b"\x10set keys.ctrl-k save\r", 1);
+    assert_eq!(row(&editor, 7), "keys.ctrl-k = save");
+
+    feed(&mut editor, b"x\x0b", 2);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "xtext\n");
+
+    // the setting's own name still takes dashes
+    feed(&mut editor, b"\x10set line-numbers absolute\r", 1);
+    assert_eq!(row(&editor, 7), "line_numbers = absolute");
+}
+
+#[test]
+fn resize_redraws_to_the_new_size() {
+    let dir = TempDir::new("resize");
+    let path = dir.file("resize.txt", "a fairly long line of text\nshort\n");
+    let mut editor = editor(&path, 8, 40);
+    assert_eq!(row(&editor, 0), "a fairly long line of text");
+
+    editor.backend_mut().resize(6, 12);
+    feed(&mut editor, b"", 1);
+    let screen = editor.backend();
+    assert_eq!(screen.text().lines().count(), 6);
+    assert!(screen.text().lines().all(|line| line.chars().count() == 12));
+    assert_eq!(row(&editor, 0), "a fairly lon");
+    assert_eq!(row(&editor, 1), "short");
+    assert!(editor.backend().cell(4, 0).style.reverse);
+    assert!(!editor.backend().cell(5, 0).style.reverse);
+}